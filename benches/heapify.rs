@@ -0,0 +1,63 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compares bottom-up heap construction against pushing elements one at a
+//! time.
+//!
+//! Prints the time taken per element for increasing input sizes.  For
+//! bottom-up construction this should stay roughly constant, demonstrating
+//! that it runs in linear time.  Run with `cargo bench --bench heapify`.
+extern crate leonardo_heap;
+extern crate rand;
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use leonardo_heap::{LeonardoHeap, heapify_slice};
+
+const SIZES: [usize; 5] = [1000, 10000, 100000, 1000000, 10000000];
+
+fn random_input(len: usize) -> Vec<u64> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+fn nanos_per_element(elapsed: Duration, len: usize) -> f64 {
+    (elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64)
+        / len as f64
+}
+
+fn bench_heapify_slice(len: usize) -> f64 {
+    let mut data = random_input(len);
+
+    let start = Instant::now();
+    heapify_slice(data.as_mut_slice());
+    nanos_per_element(start.elapsed(), len)
+}
+
+fn bench_repeated_push(len: usize) -> f64 {
+    let data = random_input(len);
+
+    let start = Instant::now();
+    let mut heap = LeonardoHeap::with_capacity(len);
+    for item in data {
+        heap.push(item);
+    }
+    nanos_per_element(start.elapsed(), len)
+}
+
+fn main() {
+    println!("{:>10} {:>16} {:>16}", "len", "heapify ns/elem", "push ns/elem");
+
+    for &len in SIZES.iter() {
+        println!(
+            "{:>10} {:>16.2} {:>16.2}",
+            len, bench_heapify_slice(len), bench_repeated_push(len),
+        );
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compares `partial_sort` against `select_nth_unstable` followed by sorting
+//! the selected elements.
+//!
+//! Prints the time taken to sort the largest `k` elements of a slice for
+//! increasing values of `k`.  Run with `cargo bench --bench partial_sort`.
+extern crate leonardo_heap;
+extern crate rand;
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use leonardo_heap::partial_sort;
+
+const LEN: usize = 1000000;
+
+const KS: [usize; 6] = [1, 10, 100, 1000, 10000, 100000];
+
+fn random_input(len: usize) -> Vec<u64> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+fn millis(elapsed: Duration) -> f64 {
+    elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 / 1e6
+}
+
+fn bench_partial_sort(input: &[u64], k: usize) -> f64 {
+    let mut data = input.to_vec();
+
+    let start = Instant::now();
+    partial_sort(data.as_mut_slice(), k);
+    millis(start.elapsed())
+}
+
+fn bench_select_nth(input: &[u64], k: usize) -> f64 {
+    let mut data = input.to_vec();
+
+    let start = Instant::now();
+    let len = data.len();
+    data.select_nth_unstable(len - k);
+    data[len - k..].sort_unstable();
+    millis(start.elapsed())
+}
+
+fn main() {
+    let input = random_input(LEN);
+
+    println!("{} elements", LEN);
+    println!("{:>10} {:>18} {:>18}", "k", "partial_sort ms", "select_nth ms");
+
+    for &k in KS.iter() {
+        println!(
+            "{:>10} {:>18.3} {:>18.3}",
+            k, bench_partial_sort(&input, k), bench_select_nth(&input, k),
+        );
+    }
+}
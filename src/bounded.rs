@@ -0,0 +1,166 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use alloc::vec::Vec;
+
+use compare::{Compare, Natural, Reversed};
+use LeonardoHeap;
+
+/// A heap that retains only the `limit` largest elements pushed on to it.
+///
+/// The elements are stored in a leonardo heap with the comparator reversed so
+/// that the smallest retained element is always on top, ready to be compared
+/// against, and if necessary evicted by, each new element.
+#[derive(Clone, Debug)]
+pub struct BoundedLeonardoHeap<T, C = Natural> {
+    heap: LeonardoHeap<T, Reversed<C>>,
+    limit: usize,
+}
+
+
+impl<T: Ord> BoundedLeonardoHeap<T> {
+    /// Creates a new, empty `BoundedLeonardoHeap<T>` that will hold at most
+    /// `limit` elements.
+    pub fn new(limit: usize) -> Self {
+        BoundedLeonardoHeap::new_by(limit, Natural)
+    }
+}
+
+
+impl<T, C: Compare<T>> BoundedLeonardoHeap<T, C> {
+    /// Creates a new, empty heap that will hold at most `limit` elements,
+    /// ordered using the comparator `cmp`.
+    pub fn new_by(limit: usize, cmp: C) -> Self {
+        BoundedLeonardoHeap {
+            heap: LeonardoHeap::new_by(Reversed(cmp)),
+            limit,
+        }
+    }
+
+    /// Returns the maximum number of elements that the heap will retain.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of elements in the heap.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Returns `true` if the heap contains no elements, `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns `true` if the heap holds `limit` elements, meaning that any
+    /// further additions will result in an eviction.
+    pub fn is_full(&self) -> bool {
+        self.heap.len() >= self.limit
+    }
+
+    /// Removes all elements from the heap.
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+
+    /// Offers a new element to the heap.
+    ///
+    /// If the heap is full then the smallest of the new element and the
+    /// currently retained elements is removed and returned.  Otherwise returns
+    /// `None`.
+    pub fn push(&mut self, item: T) -> Option<T> {
+        if self.heap.len() < self.limit {
+            self.heap.push(item);
+            None
+        } else if self.limit == 0 {
+            Some(item)
+        } else {
+            Some(self.heap.push_pop(item))
+        }
+    }
+
+    /// Returns a reference to the smallest retained element.  This is the
+    /// element that a new element must beat in order to be retained once the
+    /// heap is full.
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.data.last()
+    }
+
+    /// Removes and returns the smallest retained element.  If the heap is
+    /// empty, returns `None`.
+    pub fn pop(&mut self) -> Option<T> {
+        self.heap.pop()
+    }
+
+    /// Consumes the heap and returns a vector of the retained elements sorted
+    /// in ascending order.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        // The inner heap sorts by the reversed comparator.
+        let mut data = self.heap.into_sorted_vec();
+        data.reverse();
+        data
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use bounded::BoundedLeonardoHeap;
+    use tests::shuffled;
+
+    #[test]
+    fn test_push_evicts_smallest() {
+        let mut heap = BoundedLeonardoHeap::new(3);
+        assert_eq!(heap.push(5), None);
+        assert_eq!(heap.push(1), None);
+        assert_eq!(heap.push(3), None);
+        assert!(heap.is_full());
+
+        assert_eq!(heap.peek(), Some(&1));
+        assert_eq!(heap.push(4), Some(1));
+        assert_eq!(heap.push(2), Some(2));
+        assert_eq!(heap.push(3), Some(3));
+        assert_eq!(heap.peek(), Some(&3));
+
+        assert_eq!(heap.into_sorted_vec(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_zero_limit() {
+        let mut heap = BoundedLeonardoHeap::new(0);
+        assert_eq!(heap.push(1), Some(1));
+        assert!(heap.is_empty());
+        assert_eq!(heap.peek(), None);
+    }
+
+    #[test]
+    fn test_top_k_random() {
+        let inputs = shuffled(1000);
+
+        let mut heap = BoundedLeonardoHeap::new(100);
+        for input in inputs {
+            heap.push(input);
+        }
+        assert_eq!(heap.len(), 100);
+
+        assert_eq!(heap.into_sorted_vec(), (900..1000).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_new_by() {
+        let mut heap = BoundedLeonardoHeap::new_by(
+            2, |a: &i32, b: &i32| b.cmp(a),
+        );
+        for &input in &[5, 1, 4, 2, 3] {
+            heap.push(input);
+        }
+
+        assert_eq!(heap.pop(), Some(2));
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), None);
+    }
+}
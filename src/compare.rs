@@ -0,0 +1,83 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::cmp::Ordering;
+
+/// A comparator defining a total ordering over values of type `T`.
+///
+/// Implemented by `Natural`, which defers to `Ord`, by `Reversed` and `ByKey`,
+/// and by any closure or function of the form `Fn(&T, &T) -> Ordering`.
+pub trait Compare<T: ?Sized> {
+    /// Compares two values, returning their relative order.
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+
+/// A zero-sized comparator that orders values using their `Ord`
+/// implementation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Natural;
+
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+
+/// A comparator that inverts the ordering of the comparator that it wraps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Reversed<C>(pub C);
+
+
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Reversed<C> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+}
+
+
+impl<T: ?Sized, F> Compare<T> for F
+    where F: Fn(&T, &T) -> Ordering
+{
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+
+/// A comparator that orders values by comparing a key extracted from each.
+///
+/// The key function is called twice for each comparison.
+#[derive(Clone, Copy, Debug)]
+pub struct ByKey<F> {
+    key: F,
+}
+
+
+impl<F> ByKey<F> {
+    /// Creates a new comparator ordering values by the result of `key`.
+    pub fn new(key: F) -> Self {
+        ByKey {
+            key,
+        }
+    }
+}
+
+
+impl<T: ?Sized, K, F> Compare<T> for ByKey<F>
+    where K: Ord, F: Fn(&T) -> K
+{
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.key)(a).cmp(&(self.key)(b))
+    }
+}
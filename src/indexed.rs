@@ -0,0 +1,484 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::cmp::Ordering;
+use core::mem;
+
+use alloc::vec::Vec;
+
+use compare::{Compare, Natural};
+use layout::Layout;
+use validate;
+use {HeapOps, balance_after_push, balance_after_pop, move_to_end};
+
+/// A reference to an element in an `IndexedLeonardoHeap`.
+///
+/// A handle remains valid until the element that it refers to is popped or
+/// removed from the heap.  After that the heap treats it as referring to
+/// nothing, even if its slot is given to a newly pushed element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: usize,
+    generation: u64,
+}
+
+
+#[derive(Clone, Debug)]
+struct Entry<T> {
+    value: T,
+
+    /// The index in `positions` that records where this entry is stored.
+    slot: usize,
+}
+
+
+/// Implements the heap operations for the entries of an indexed heap,
+/// recording the new position of each entry as it is moved.
+struct Tracker<'a, C: 'a> {
+    cmp: &'a C,
+    positions: &'a mut [Option<usize>],
+}
+
+
+impl<'a, C> Tracker<'a, C> {
+    fn new(positions: &'a mut [Option<usize>], cmp: &'a C) -> Self {
+        Tracker {
+            cmp,
+            positions,
+        }
+    }
+}
+
+
+impl<'a, T, C: Compare<T>> HeapOps<Entry<T>> for Tracker<'a, C> {
+    #[inline]
+    fn is_less(&mut self, a: &Entry<T>, b: &Entry<T>) -> bool {
+        self.cmp.compare(&a.value, &b.value) == Ordering::Less
+    }
+
+    #[inline]
+    fn swap(
+        &mut self, a: &mut Entry<T>, a_index: usize,
+        b: &mut Entry<T>, b_index: usize,
+    ) {
+        mem::swap(a, b);
+
+        self.positions[a.slot] = Some(a_index);
+        self.positions[b.slot] = Some(b_index);
+    }
+}
+
+
+/// A leonardo heap that returns a `Handle` for each element pushed on to it,
+/// which can later be used to look up, update or remove the element.
+///
+/// This makes it suitable for algorithms such as Dijkstra's that need to
+/// change the priority of items already in the queue.
+#[derive(Clone, Debug)]
+pub struct IndexedLeonardoHeap<T, C = Natural> {
+    data: Vec<Entry<T>>,
+    layout: Layout,
+    cmp: C,
+
+    /// Maps each handle to the index of its entry in `data`, or `None` if the
+    /// handle is not currently in use.
+    positions: Vec<Option<usize>>,
+
+    /// The generation of each slot, bumped every time the slot is released
+    /// so that handles given out for earlier elements no longer match.
+    generations: Vec<u64>,
+
+    /// Handles that are not currently in use and can be given out again.
+    free_slots: Vec<usize>,
+}
+
+
+impl<T: Ord> IndexedLeonardoHeap<T> {
+    /// Creates a new, empty `IndexedLeonardoHeap<T>`.
+    pub fn new() -> Self {
+        IndexedLeonardoHeap::new_by(Natural)
+    }
+}
+
+
+impl<T, C: Compare<T>> IndexedLeonardoHeap<T, C> {
+    /// Creates a new, empty heap that orders its elements using the
+    /// comparator `cmp`.
+    pub fn new_by(cmp: C) -> Self {
+        IndexedLeonardoHeap {
+            data: Vec::new(),
+            layout: Layout::new(),
+            cmp,
+            positions: Vec::new(),
+            generations: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    /// Returns the number of elements in the heap.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the heap contains no elements, `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Removes all elements from the heap, invalidating all handles.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.layout = Layout::new();
+
+        // Slots are released rather than forgotten so that their generations
+        // carry on increasing and old handles stay invalid.
+        for slot in 0..self.positions.len() {
+            if self.positions[slot].is_some() {
+                self.release(slot);
+            }
+        }
+    }
+
+    /// Returns `true` if `handle` refers to an element in the heap.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.position(handle).is_some()
+    }
+
+    /// Returns a reference to the element that `handle` refers to, or `None`
+    /// if it is no longer in the heap.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.position(handle).map(|index| &self.data[index].value)
+    }
+
+    /// Adds a new element to the heap, returning a handle that can be used to
+    /// refer to it for as long as it remains in the heap.
+    pub fn push(&mut self, value: T) -> Handle {
+        let slot = match self.free_slots.pop() {
+            Some(slot) => slot,
+            None => {
+                self.positions.push(None);
+                self.generations.push(0);
+                self.positions.len() - 1
+            }
+        };
+
+        self.data.push(Entry {
+            value,
+            slot,
+        });
+        self.layout.push();
+        self.positions[slot] = Some(self.data.len() - 1);
+
+        let mut tracker = Tracker::new(&mut self.positions, &self.cmp);
+        balance_after_push(
+            self.data.as_mut_slice(), &self.layout, &mut tracker,
+        );
+        self.paranoid_check();
+
+        self.handle(slot)
+    }
+
+    /// Returns the handle of the largest element in the heap, and a reference
+    /// to it, without removing it.
+    pub fn peek(&self) -> Option<(Handle, &T)> {
+        self.data.last().map(|entry| (self.handle(entry.slot), &entry.value))
+    }
+
+    /// Removes the largest element from the heap and returns it along with
+    /// the handle that referred to it.  If the heap is empty, returns `None`.
+    pub fn pop(&mut self) -> Option<(Handle, T)> {
+        let entry = self.data.pop()?;
+        self.layout.pop();
+        let handle = self.handle(entry.slot);
+        self.release(entry.slot);
+
+        let mut tracker = Tracker::new(&mut self.positions, &self.cmp);
+        balance_after_pop(
+            self.data.as_mut_slice(), &self.layout, &mut tracker,
+        );
+        self.paranoid_check();
+
+        Some((handle, entry.value))
+    }
+
+    /// Removes the element that `handle` refers to from the heap and returns
+    /// it, or returns `None` if it is no longer in the heap.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let index = self.position(handle)?;
+
+        {
+            let mut tracker = Tracker::new(&mut self.positions, &self.cmp);
+            move_to_end(
+                self.data.as_mut_slice(), &self.layout, index, &mut tracker,
+            );
+        }
+
+        let entry = self.data.pop().unwrap();
+        self.layout.pop();
+        self.release(entry.slot);
+        self.paranoid_check();
+
+        Some(entry.value)
+    }
+
+    /// Replaces the element that `handle` refers to with a new value,
+    /// returning the old value.  The handle remains valid and will refer to
+    /// the new value.
+    ///
+    /// The new value may be larger or smaller than the old one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` does not refer to an element in the heap.
+    pub fn update(&mut self, handle: Handle, value: T) -> T {
+        let index = self.position(handle)
+            .expect("handle does not refer to an element in the heap");
+
+        let mut tracker = Tracker::new(&mut self.positions, &self.cmp);
+
+        // Take the entry out of the heap, leaving it at the end of the data,
+        // then put it straight back again with its new value.
+        move_to_end(
+            self.data.as_mut_slice(), &self.layout, index, &mut tracker,
+        );
+
+        let result = mem::replace(
+            &mut self.data.last_mut().unwrap().value, value,
+        );
+
+        balance_after_push(
+            self.data.as_mut_slice(), &self.layout, &mut tracker,
+        );
+        self.paranoid_check();
+
+        result
+    }
+
+    /// Panics if the heap is invalid and the `paranoid` feature is enabled.
+    fn paranoid_check(&self) {
+        let cmp = &self.cmp;
+        validate::paranoid_check(
+            &self.data, &self.layout, &mut |a: &Entry<T>, b: &Entry<T>| {
+                cmp.compare(&a.value, &b.value) == Ordering::Less
+            },
+        );
+    }
+
+    fn handle(&self, slot: usize) -> Handle {
+        Handle {
+            slot,
+            generation: self.generations[slot],
+        }
+    }
+
+    fn position(&self, handle: Handle) -> Option<usize> {
+        match self.generations.get(handle.slot) {
+            Some(&generation) if generation == handle.generation => {
+                self.positions[handle.slot]
+            }
+            _ => None,
+        }
+    }
+
+    fn release(&mut self, slot: usize) {
+        self.positions[slot] = None;
+        self.generations[slot] = self.generations[slot].wrapping_add(1);
+        self.free_slots.push(slot);
+    }
+}
+
+
+impl<T, C: Compare<T> + Default> Default for IndexedLeonardoHeap<T, C> {
+    /// Creates an empty heap using the default comparator.
+    fn default() -> Self {
+        IndexedLeonardoHeap::new_by(C::default())
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use rand;
+    use rand::Rng;
+
+    use indexed::{IndexedLeonardoHeap, Handle};
+
+    /// Checks that every handle in use points to the entry that refers back
+    /// to it.
+    fn check_positions<T>(heap: &IndexedLeonardoHeap<T>) {
+        for (slot, position) in heap.positions.iter().enumerate() {
+            if let Some(index) = *position {
+                assert_eq!(heap.data[index].slot, slot);
+            }
+        }
+
+        let in_use = heap.positions.iter().filter(|p| p.is_some()).count();
+        assert_eq!(in_use, heap.data.len());
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut heap = IndexedLeonardoHeap::new();
+        let four = heap.push(4);
+        let one = heap.push(1);
+        let two = heap.push(2);
+        let three = heap.push(3);
+        check_positions(&heap);
+
+        assert_eq!(heap.peek(), Some((four, &4)));
+        assert_eq!(heap.pop(), Some((four, 4)));
+        assert_eq!(heap.pop(), Some((three, 3)));
+        assert_eq!(heap.pop(), Some((two, 2)));
+        assert_eq!(heap.pop(), Some((one, 1)));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_get() {
+        let mut heap = IndexedLeonardoHeap::new();
+        let handles: Vec<Handle> = (0..20).map(|i| heap.push(i)).collect();
+
+        for (i, &handle) in handles.iter().enumerate() {
+            assert_eq!(heap.get(handle), Some(&(i as i32)));
+        }
+
+        heap.pop();
+        assert!(!heap.contains(handles[19]));
+        assert_eq!(heap.get(handles[19]), None);
+        assert_eq!(heap.get(handles[18]), Some(&18));
+    }
+
+    #[test]
+    fn test_update() {
+        let mut heap = IndexedLeonardoHeap::new();
+        let handles: Vec<Handle> = (0..10).map(|i| heap.push(i)).collect();
+
+        // Decrease the largest element and increase the smallest.
+        assert_eq!(heap.update(handles[9], -1), 9);
+        assert_eq!(heap.update(handles[0], 20), 0);
+        check_positions(&heap);
+
+        assert_eq!(heap.get(handles[9]), Some(&-1));
+        assert_eq!(heap.pop(), Some((handles[0], 20)));
+        assert_eq!(heap.pop(), Some((handles[8], 8)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_update_removed() {
+        let mut heap = IndexedLeonardoHeap::new();
+        let handle = heap.push(1);
+        heap.pop();
+        heap.update(handle, 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_update_stale() {
+        let mut heap = IndexedLeonardoHeap::new();
+        let handle = heap.push(1);
+        heap.pop();
+        heap.push(2);
+        heap.update(handle, 3);
+    }
+
+    #[test]
+    fn test_stale_handle() {
+        let mut heap = IndexedLeonardoHeap::new();
+        let stale = heap.push(1);
+        heap.pop();
+
+        // The new element reuses the slot, but not the handle.
+        let fresh = heap.push(2);
+        assert_ne!(stale, fresh);
+        assert!(!heap.contains(stale));
+        assert_eq!(heap.get(stale), None);
+        assert_eq!(heap.remove(stale), None);
+        assert_eq!(heap.get(fresh), Some(&2));
+
+        heap.clear();
+        assert_eq!(heap.get(fresh), None);
+        let newest = heap.push(3);
+        assert_eq!(heap.get(fresh), None);
+        assert_eq!(heap.get(newest), Some(&3));
+        check_positions(&heap);
+    }
+
+    #[test]
+    fn test_default() {
+        let mut heap: IndexedLeonardoHeap<i32> = Default::default();
+        assert!(heap.is_empty());
+
+        let handle = heap.push(1);
+        assert_eq!(heap.pop(), Some((handle, 1)));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut heap = IndexedLeonardoHeap::new();
+        let handles: Vec<Handle> = (0..10).map(|i| heap.push(i)).collect();
+
+        assert_eq!(heap.remove(handles[3]), Some(3));
+        assert_eq!(heap.remove(handles[3]), None);
+        assert_eq!(heap.remove(handles[9]), Some(9));
+        assert_eq!(heap.remove(handles[0]), Some(0));
+        check_positions(&heap);
+
+        let mut outputs = Vec::new();
+        while let Some((_, value)) = heap.pop() {
+            outputs.push(value);
+        }
+        assert_eq!(outputs, vec![8, 7, 6, 5, 4, 2, 1]);
+    }
+
+    #[test]
+    fn test_random_operations() {
+        let mut rng = rand::thread_rng();
+
+        let mut heap = IndexedLeonardoHeap::new();
+        let mut expected: Vec<(Handle, i32)> = Vec::new();
+
+        for _ in 0..2000 {
+            match rng.gen_range(0, 4) {
+                0 | 1 => {
+                    let value = rng.gen_range(0, 1000);
+                    let handle = heap.push(value);
+                    expected.push((handle, value));
+                }
+                2 if !expected.is_empty() => {
+                    let i = rng.gen_range(0, expected.len());
+                    let value = rng.gen_range(0, 1000);
+                    let (handle, old) = expected[i];
+                    assert_eq!(heap.update(handle, value), old);
+                    expected[i] = (handle, value);
+                }
+                3 if !expected.is_empty() => {
+                    let i = rng.gen_range(0, expected.len());
+                    let (handle, old) = expected.swap_remove(i);
+                    assert_eq!(heap.remove(handle), Some(old));
+                }
+                _ => {}
+            }
+
+            check_positions(&heap);
+            for &(handle, value) in &expected {
+                assert_eq!(heap.get(handle), Some(&value));
+            }
+        }
+
+        let mut expected: Vec<i32> = expected.iter()
+            .map(|&(_, value)| value)
+            .collect();
+        expected.sort_by(|a, b| b.cmp(a));
+
+        let mut outputs = Vec::new();
+        while let Some((_, value)) = heap.pop() {
+            outputs.push(value);
+        }
+        assert_eq!(outputs, expected);
+    }
+}
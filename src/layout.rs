@@ -5,11 +5,11 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::fmt::Debug;
-use std::mem;
+use core::fmt;
+use core::mem;
 
 use leonardo::leonardo;
-use subheap::SubHeapMut;
+use subheap::{SubHeap, SubHeapMut};
 
 /// The `Layout` structure encapsulates the logic and state describing how a
 /// heap is broken down into subheaps.
@@ -17,7 +17,7 @@ use subheap::SubHeapMut;
 /// A heap is made up of a number of subheaps of increasing order.
 /// When three adjacent subheaps have contiguous orders they are merged into one
 /// subheap of the next order.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Layout {
     /// A bitmask describing what order of subheaps are present at the top level
     /// of this heap.
@@ -48,8 +48,8 @@ impl Layout {
         }
 
         Layout {
-            orders: orders,
-            size: size,
+            orders,
+            size,
         }
     }
 
@@ -116,9 +116,56 @@ impl Layout {
         }
     }
 
+    /// Returns the orders of the top-level subheaps, starting with the largest
+    /// subheap at the start of the data and ending with the smallest.
+    pub fn orders(&self) -> impl Iterator<Item = u32> {
+        let orders = self.orders;
+        (0..64).rev().filter(move |order| orders & (1 << order) != 0)
+    }
+
+    /// Returns the order of the largest subheap, at any depth, that starts at
+    /// `offset`.  Returns `None` if `offset` is out of range or is the head of
+    /// a subheap with more than one value.
+    pub fn subheap_order_at(&self, offset: usize) -> Option<u32> {
+        let mut start = 0;
+
+        for order in self.orders() {
+            let len = leonardo(order);
+
+            if offset < start + len {
+                let mut order = order;
+
+                // Descend towards the subheap that starts at `offset`.  The
+                // second, larger, child comes first in the data.
+                while offset != start {
+                    let snd_len = leonardo(order - 1);
+                    let fst_len = leonardo(order - 2);
+
+                    if offset < start + snd_len {
+                        order -= 1;
+                    } else if offset < start + snd_len + fst_len {
+                        start += snd_len;
+                        order -= 2;
+                    } else {
+                        return None;
+                    }
+                }
+
+                return Some(order);
+            }
+
+            start += len;
+        }
+
+        None
+    }
+
     /// Breaks the data into top-level subheaps to be iterated over in order
     /// from smallest to largest.
-    pub fn iter<'a, T : Ord + Debug>(
+    ///
+    /// The offset of each subheap is its position relative to the start of
+    /// `data`.
+    pub fn iter<'a, T>(
         &self, data : &'a mut [T],
     ) -> IterMut<'a, T> {
         assert_eq!(data.len(), self.len());
@@ -128,9 +175,78 @@ impl Layout {
             orders: self.orders,
         }
     }
+
+    /// Breaks the data into top-level subheaps to be iterated over in order
+    /// from smallest to largest, without needing to borrow it mutably.
+    pub fn iter_ref<'a, T>(&self, data : &'a [T]) -> Iter<'a, T> {
+        assert_eq!(data.len(), self.len());
+
+        Iter {
+            heap_data: data,
+            orders: self.orders,
+        }
+    }
+}
+
+
+/// Lists the orders of the top-level subheaps, largest first, rather than the
+/// raw bitmask.
+impl fmt::Debug for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        struct Orders<'a>(&'a Layout);
+
+        impl<'a> fmt::Debug for Orders<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_list().entries(self.0.orders()).finish()
+            }
+        }
+
+        f.debug_struct("Layout")
+            .field("orders", &Orders(self))
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+
+#[derive(Debug)]
+pub struct Iter<'a, T: 'a> {
+    heap_data: &'a [T],
+    orders: u64,
+}
+
+
+impl<'a, T> Iterator for Iter<'a, T>
+{
+    type Item = SubHeap<'a, T>;
+
+    fn next(&mut self) -> Option<SubHeap<'a, T>> {
+        if self.orders != 0 {
+            // The order of the sub-heap at the end of what remains.
+            let order = self.orders.trailing_zeros();
+            self.orders ^= 1 << order;
+
+            let (rest_data, subheap_data) = self.heap_data.split_at(
+                self.heap_data.len() - leonardo(order)
+            );
+            self.heap_data = rest_data;
+
+            Some(SubHeap::new(subheap_data, order))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let ones = self.orders.count_ones() as usize;
+        (ones, Some(ones))
+    }
 }
 
 
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+
 #[derive(Debug)]
 pub struct IterMut<'a, T: 'a> {
     heap_data: &'a mut [T],
@@ -138,7 +254,7 @@ pub struct IterMut<'a, T: 'a> {
 }
 
 
-impl<'a, T : Ord + Debug> Iterator for IterMut<'a, T>
+impl<'a, T> Iterator for IterMut<'a, T>
 {
     type Item = SubHeapMut<'a, T>;
 
@@ -157,7 +273,7 @@ impl<'a, T : Ord + Debug> Iterator for IterMut<'a, T>
             // In order to avoid having more than one mutable reference to the
             // heap at any one time,we have to temporarily replace it in self
             // with a placeholder value.
-            let heap_data = mem::replace(&mut self.heap_data, &mut []);
+            let heap_data = mem::take(&mut self.heap_data);
 
             // Split the heap into the part belonging to this sub-heap and all
             // of the rest.
@@ -165,10 +281,12 @@ impl<'a, T : Ord + Debug> Iterator for IterMut<'a, T>
                 heap_len - leonardo(order)
             );
 
+            let offset = rest_data.len();
+
             // Store what's left of the heap back in self.
             self.heap_data = rest_data;
 
-            Some(SubHeapMut::new(subheap_data, order))
+            Some(SubHeapMut::new_at(subheap_data, order, offset))
         } else {
             None
         }
@@ -181,4 +299,61 @@ impl<'a, T : Ord + Debug> Iterator for IterMut<'a, T>
 }
 
 
-impl<'a, T : Ord + Debug> ExactSizeIterator for IterMut<'a, T> {}
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use layout::Layout;
+
+    #[test]
+    fn test_new_from_len_matches_push() {
+        let mut layout = Layout::new();
+        for len in 1..10000 {
+            layout.push();
+
+            let expected = Layout::new_from_len(len);
+            assert_eq!(layout.orders, expected.orders);
+            assert_eq!(layout.size, expected.size);
+        }
+    }
+
+    #[test]
+    fn test_orders() {
+        let layout = Layout::new_from_len(13);
+        assert_eq!(layout.orders().collect::<Vec<u32>>(), vec![4, 2, 1]);
+
+        let layout = Layout::new_from_len(0);
+        assert_eq!(layout.orders().count(), 0);
+    }
+
+    #[test]
+    fn test_debug() {
+        let layout = Layout::new_from_len(13);
+        assert_eq!(
+            format!("{:?}", layout), "Layout { orders: [4, 2, 1], size: 13 }",
+        );
+    }
+
+    #[test]
+    fn test_subheap_order_at() {
+        // A single subheap of order 4, made up of subheaps of order 3 and 2.
+        let layout = Layout::new_from_len(9);
+
+        assert_eq!(layout.subheap_order_at(0), Some(4));
+        assert_eq!(layout.subheap_order_at(1), Some(0));
+        assert_eq!(layout.subheap_order_at(2), None);
+        assert_eq!(layout.subheap_order_at(3), Some(1));
+        assert_eq!(layout.subheap_order_at(4), None);
+        assert_eq!(layout.subheap_order_at(5), Some(2));
+        assert_eq!(layout.subheap_order_at(6), Some(0));
+        assert_eq!(layout.subheap_order_at(7), None);
+        assert_eq!(layout.subheap_order_at(8), None);
+        assert_eq!(layout.subheap_order_at(9), None);
+
+        // Subheaps of order 4, 2 and 1.
+        let layout = Layout::new_from_len(13);
+
+        assert_eq!(layout.subheap_order_at(9), Some(2));
+        assert_eq!(layout.subheap_order_at(12), Some(1));
+    }
+}
@@ -26,19 +26,21 @@ fn leonardo_lookup(order: u32) -> usize {
 
 /// Closed form implementation of function for determining the nth leonardo
 /// number.
+#[cfg(all(test, feature = "std"))]
 #[inline]
 fn leonardo_closed(order: u32) -> usize {
     // TODO this starts to diverge due to precision issues at higher orders.
     // Need to figure out how far it is accurate, and raise an assertion error.
-    return (
+    (
         2.0 * (
             ((1.0 + 5.0f64.sqrt()) / 2.0).powf(order as f64 + 1.0) -
             ((1.0 - 5.0f64.sqrt()) / 2.0).powf(order as f64 + 1.0)
         ) / 5.0f64.sqrt()
-    ).floor() as usize - 1;
+    ).floor() as usize - 1
 }
 
 /// Iterative function for determining the nth leonardo number.
+#[cfg(all(test, feature = "std"))]
 #[inline]
 fn leonardo_naive(order: u32) -> usize {
     if order < 2 {
@@ -63,10 +65,10 @@ fn leonardo_naive(order: u32) -> usize {
 /// Returns the nth leonardo number.
 /// Only defined for order less than 64.
 pub fn leonardo(order: u32) -> usize {
-    return leonardo_lookup(order);
+    leonardo_lookup(order)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use leonardo::{leonardo_lookup, leonardo_closed, leonardo_naive};
 
@@ -8,20 +8,116 @@
 //! A binary heap structure supporting fast in-place partial sorting.
 //!
 //! This is structure is the core of Dijkstra's Smoothsort algorithm.
-#[cfg(test)]
+//!
+//! The crate is `no_std` compatible.  Without the `alloc` feature a
+//! `LeonardoHeap` can still be used with fixed capacity storage, and the slice
+//! sorting functions are always available.  The `std` feature, which implies
+//! `alloc`, is enabled by default.
+//!
+//! The optional `serde` feature implements `Serialize` and `Deserialize` for
+//! `LeonardoHeap`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(all(test, feature = "std"))]
 extern crate rand;
 
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_test;
+
 mod leonardo;
 mod subheap;
 mod layout;
+mod compare;
+mod storage;
+mod sorter;
+mod validate;
+#[cfg(feature = "alloc")]
+mod bounded;
+#[cfg(feature = "alloc")]
+mod indexed;
+#[cfg(feature = "serde")]
+mod serialize;
+
+use core::cmp;
+use core::cmp::Ordering;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::slice;
+
+#[cfg(feature = "alloc")]
+use core::iter::FromIterator;
+
+#[cfg(feature = "alloc")]
+use alloc::collections::TryReserveError;
+#[cfg(feature = "alloc")]
+use alloc::vec::{self, Vec};
+
+use leonardo::leonardo;
+use subheap::SubHeapMut;
 
-use std::fmt::Debug;
+pub use compare::{Compare, Natural, Reversed, ByKey};
+pub use storage::{Storage, SliceStorage, ArrayStorage};
+pub use sorter::PartialSorter;
+pub use validate::InvariantError;
+#[cfg(feature = "alloc")]
+pub use bounded::BoundedLeonardoHeap;
+#[cfg(feature = "alloc")]
+pub use indexed::{IndexedLeonardoHeap, Handle};
+
+/// The operations used by the functions that maintain the heap to compare and
+/// move values.
+///
+/// Implemented for any `is_less` predicate, in which case values are moved
+/// with a plain `mem::swap`.  `IndexedLeonardoHeap` provides its own
+/// implementation that keeps track of where each value ends up.
+trait HeapOps<T> {
+    /// Returns `true` if `a` should be ordered below `b`.
+    fn is_less(&mut self, a: &T, b: &T) -> bool;
+
+    /// Exchanges two values in the heap, found at `a_index` and `b_index` in
+    /// its data.
+    #[inline]
+    fn swap(
+        &mut self, a: &mut T, _a_index: usize, b: &mut T, _b_index: usize,
+    ) {
+        mem::swap(a, b);
+    }
+}
 
-use subheap::SubHeapMut;
+
+impl<T, F> HeapOps<T> for F
+    where F: FnMut(&T, &T) -> bool
+{
+    #[inline]
+    fn is_less(&mut self, a: &T, b: &T) -> bool {
+        self(a, b)
+    }
+}
+
+
+/// Adapts a comparator into the `is_less` predicate expected by the functions
+/// that maintain the heap.
+fn is_less<'a, T, C>(cmp: &'a C) -> impl Fn(&T, &T) -> bool + 'a
+    where C: Compare<T>
+{
+    move |a, b| cmp.compare(a, b) == Ordering::Less
+}
 
 /// Recursively move a new top element down the heap to restore heap order
 /// within a subheap.
-fn sift_down<T: Ord + Debug>(heap: &mut SubHeapMut<T>) {
+fn sift_down<T, O>(heap: &mut SubHeapMut<T>, ops: &mut O)
+    where O: HeapOps<T>
+{
+    let mut this_index = heap.head_index();
     let (mut this_value, mut children) = heap.destructure_mut();
 
     loop {
@@ -34,27 +130,24 @@ fn sift_down<T: Ord + Debug>(heap: &mut SubHeapMut<T>) {
 
         // Find the largest child.  Prefer the furthest child if both children
         // are the same as doing so makes the array slightly more sorted.
-        let mut next_heap = if fst_child.value() > snd_child.value() {
-            fst_child
-        } else {
-            snd_child
-        };
+        let mut next_heap =
+            if ops.is_less(snd_child.value(), fst_child.value()) {
+                fst_child
+            } else {
+                snd_child
+            };
 
         // The heap property is satisfied.  No need to do anything else.
-        if &*this_value >= next_heap.value() {
+        if !ops.is_less(this_value, next_heap.value()) {
             break;
         }
 
         // Swap the value of the parent with the value of the largest child.
-        std::mem::swap(this_value, next_heap.value_mut());
+        let next_index = next_heap.head_index();
+        ops.swap(this_value, this_index, next_heap.value_mut(), next_index);
 
-        // TODO there has to be a better pattern for unpacking to existing vars
-        match next_heap.into_components() {
-            (v, n) => {
-                this_value = v;
-                children = n;
-            }
-        }
+        this_index = next_index;
+        (this_value, children) = next_heap.into_components();
     }
 }
 
@@ -62,21 +155,28 @@ fn sift_down<T: Ord + Debug>(heap: &mut SubHeapMut<T>) {
 /// of each each top-level subheap should contain a value greater than the head
 /// of the next subheap down after the head of the first subheap has been
 /// changed.  It assumes that the heap property alread holds for all subheaps.
-fn restring<T : Ord + Debug>(mut subheap_iter: layout::IterMut<T>) {
+fn restring<T, O>(mut subheap_iter: layout::IterMut<T>, ops: &mut O)
+    where O: HeapOps<T>
+{
     if let Some(mut this_subheap) = subheap_iter.next() {
         for mut next_subheap in subheap_iter {
-            if next_subheap.value() <= this_subheap.value() {
+            if !ops.is_less(this_subheap.value(), next_subheap.value()) {
                 break;
             }
 
-            std::mem::swap(next_subheap.value_mut(), this_subheap.value_mut());
+            let next_index = next_subheap.head_index();
+            let this_index = this_subheap.head_index();
+            ops.swap(
+                next_subheap.value_mut(), next_index,
+                this_subheap.value_mut(), this_index,
+            );
 
             // The head of `next_subheap` is now lower than it was previously
             // and so may need to be moved down.  As the new value at the head
             // of `this_subheap` was larger than the old value it will already
             // be in heap order so there is no need to do the same for
             // `this_subheap`.
-            sift_down(&mut next_subheap);
+            sift_down(&mut next_subheap, ops);
 
             this_subheap = next_subheap;
         }
@@ -85,23 +185,27 @@ fn restring<T : Ord + Debug>(mut subheap_iter: layout::IterMut<T>) {
 
 /// Restores the heap property of the first subheap and the string property of
 /// the heap as a whole after a push.
-fn balance_after_push<T: Ord + Debug>(
-    heap_data: &mut [T], layout: &layout::Layout,
-) {
+fn balance_after_push<T, O>(
+    heap_data: &mut [T], layout: &layout::Layout, ops: &mut O,
+)
+    where O: HeapOps<T>
+{
     assert_eq!(heap_data.len(), layout.len());
 
     // Move the highest value in the first subheap to the top.
-    sift_down(&mut layout.iter(heap_data).next().unwrap());
+    sift_down(&mut layout.iter(heap_data).next().unwrap(), ops);
 
     // Swap it down through the other top-level subheaps until the string
     // property is restored.
-    restring(layout.iter(heap_data));
+    restring(layout.iter(heap_data), ops);
 }
 
 /// Restores the string property after a pop.
-fn balance_after_pop<T: Ord + Debug>(
-    heap_data: &mut [T], layout: &layout::Layout,
-) {
+fn balance_after_pop<T, O>(
+    heap_data: &mut [T], layout: &layout::Layout, ops: &mut O,
+)
+    where O: HeapOps<T>
+{
     {
         let mut subheap_iter = layout.iter(heap_data);
         match (subheap_iter.next(), subheap_iter.next()) {
@@ -121,42 +225,395 @@ fn balance_after_pop<T: Ord + Debug>(
         // Consume the first subheap.
         subheaps_from_snd.next();
 
-        restring(subheaps_from_snd);
+        restring(subheaps_from_snd, ops);
     }
 
     {
         let subheaps_from_fst = layout.iter(heap_data);
-        restring(subheaps_from_fst);
+        restring(subheaps_from_fst, ops);
+    }
+}
+
+/// Moves the value at `index`, relative to the start of the subheap, up to the
+/// head of the subheap by swapping it with each of its ancestors in turn.
+///
+/// Each ancestor moves down one level into a position below it, so the heap
+/// property continues to hold for every value other than the one being moved.
+fn raise_to_head<T, O>(heap: &mut SubHeapMut<T>, index: usize, ops: &mut O)
+    where O: HeapOps<T>
+{
+    let head_index = heap.head_index();
+    let (value, children) = heap.destructure_mut();
+
+    if let Some((fst_child, snd_child)) = children {
+        // The second, larger, child comes first in the underlying data.
+        let snd_len = leonardo(snd_child.order);
+        let fst_len = leonardo(fst_child.order);
+
+        let (mut child, child_index) = if index < snd_len {
+            (snd_child, index)
+        } else if index < snd_len + fst_len {
+            (fst_child, index - snd_len)
+        } else {
+            // The value is already at the head.
+            return;
+        };
+
+        raise_to_head(&mut child, child_index, ops);
+
+        let child_head_index = child.head_index();
+        ops.swap(value, head_index, child.value_mut(), child_head_index);
+    }
+}
+
+/// Moves the value at `index` to the end of the heap so that it can be popped
+/// off, and then restores the heap and string properties for the values in
+/// front of it.
+///
+/// The value is treated as if it were larger than everything else in the heap.
+/// It is raised to the head of its top-level subheap, and then passed along
+/// the heads of the subheaps after it until it reaches the end.  Once it has
+/// been excluded, the remaining values are in the same state as after a pop.
+fn move_to_end<T, O>(
+    heap_data: &mut [T], layout: &layout::Layout, index: usize, ops: &mut O,
+)
+    where O: HeapOps<T>
+{
+    assert_eq!(heap_data.len(), layout.len());
+    assert!(index < heap_data.len());
+
+    // Find the top-level subheap containing the value, counting from the end
+    // of the heap, and move the value to its head.
+    let mut position = 0;
+    {
+        let mut subheap_end = heap_data.len();
+        for mut subheap in layout.iter(heap_data) {
+            let subheap_start = subheap_end - leonardo(subheap.order);
+
+            if index >= subheap_start {
+                raise_to_head(&mut subheap, index - subheap_start, ops);
+                break;
+            }
+
+            subheap_end = subheap_start;
+            position += 1;
+        }
+    }
+
+    // Rotate the heads of the subheaps between it and the end of the heap so
+    // that each moves along by one and the value ends up at the very end.
+    {
+        let mut subheap_iter = layout.iter(heap_data);
+        let mut fst_subheap = subheap_iter.next().unwrap();
+
+        let fst_index = fst_subheap.head_index();
+        for mut subheap in subheap_iter.take(position) {
+            let index = subheap.head_index();
+            ops.swap(
+                fst_subheap.value_mut(), fst_index, subheap.value_mut(), index,
+            );
+        }
+    }
+
+    let mut remaining_layout = layout.clone();
+    remaining_layout.pop();
+
+    let remaining_len = remaining_layout.len();
+    balance_after_pop(
+        &mut heap_data[0..remaining_len], &remaining_layout, ops,
+    );
+}
+
+/// Establishes the heap property for a subheap containing values in arbitrary
+/// order by sifting down from the leaves upwards.
+fn heapify_subheap<T, O>(heap: &mut SubHeapMut<T>, ops: &mut O)
+    where O: HeapOps<T>
+{
+    if let Some((mut fst_child, mut snd_child)) = heap.children_mut() {
+        heapify_subheap(&mut fst_child, ops);
+        heapify_subheap(&mut snd_child, ops);
+    }
+
+    sift_down(heap, ops);
+}
+
+/// Rearranges a slice of values in arbitrary order into a leonardo heap in
+/// linear time, returning the layout of the new heap.
+///
+/// Each top-level subheap is built independently from the bottom up.  The
+/// string property is then established once at the end by inserting the head
+/// of each subheap in turn, working from the largest down to the smallest.
+fn build_heap<T, O>(heap_data: &mut [T], ops: &mut O) -> layout::Layout
+    where O: HeapOps<T>
+{
+    let layout = layout::Layout::new_from_len(heap_data.len());
+
+    for mut subheap in layout.iter(heap_data) {
+        heapify_subheap(&mut subheap, ops);
+    }
+
+    restring_from(heap_data, &layout, 0, ops);
+
+    layout
+}
+
+/// Establishes the string property for a heap in which every top-level
+/// subheap is already in heap order, and the heads of the first `skip`
+/// top-level subheaps are already in string order.
+///
+/// The head of each remaining subheap is inserted in turn, working from the
+/// largest subheap down to the smallest.
+fn restring_from<T, O>(
+    heap_data: &mut [T], layout: &layout::Layout, skip: usize, ops: &mut O,
+)
+    where O: HeapOps<T>
+{
+    assert_eq!(heap_data.len(), layout.len());
+
+    let mut prefix_len = 0;
+    for (position, order) in layout.orders().enumerate() {
+        prefix_len += leonardo(order);
+
+        if position < skip {
+            continue;
+        }
+
+        let prefix_layout = layout::Layout::new_from_len(prefix_len);
+        restring(
+            prefix_layout.iter(&mut heap_data[0..prefix_len]), ops,
+        );
+    }
+}
+
+/// Re-establishes the heap property for a subheap after the values from
+/// `dirty` onwards have been replaced.
+///
+/// Values before `dirty` are assumed to be where `previous` put them.  Any
+/// subheap that lies entirely before `dirty` and lines up with a subheap of
+/// the same or greater order in `previous` is already in heap order, and is
+/// skipped.
+fn reheapify_subheap<T, O>(
+    heap: &mut SubHeapMut<T>, dirty: usize, previous: &layout::Layout,
+    ops: &mut O,
+)
+    where O: HeapOps<T>
+{
+    if heap.head_index() < dirty {
+        if let Some(order) = previous.subheap_order_at(heap.offset) {
+            if order >= heap.order {
+                return;
+            }
+        }
     }
+
+    if let Some((mut fst_child, mut snd_child)) = heap.children_mut() {
+        reheapify_subheap(&mut snd_child, dirty, previous, ops);
+        reheapify_subheap(&mut fst_child, dirty, previous, ops);
+    }
+
+    sift_down(heap, ops);
+}
+
+/// Moves the `n` largest values in a heap to the end of the data, in ascending
+/// order, by popping them off one at a time.
+///
+/// `layout` is updated to describe the heap made up of the values left in
+/// front of them.
+fn sort_top<T, O>(
+    heap_data: &mut [T], layout: &mut layout::Layout, n: usize, ops: &mut O,
+)
+    where O: HeapOps<T>
+{
+    assert_eq!(heap_data.len(), layout.len());
+    assert!(n <= heap_data.len());
+
+    let len = heap_data.len();
+    for end in (len - n..len).rev() {
+        layout.pop();
+        balance_after_pop(&mut heap_data[0..end], layout, ops);
+    }
+}
+
+/// Sorts a slice that is already arranged as a leonardo heap with the given
+/// layout by repeatedly popping the largest remaining element off the end.
+fn sort_heap<T, O>(
+    heap_data: &mut [T], mut layout: layout::Layout, ops: &mut O,
+)
+    where O: HeapOps<T>
+{
+    let len = heap_data.len();
+    sort_top(heap_data, &mut layout, len, ops);
+}
+
+/// Sorts a slice in place using smoothsort, first arranging the slice into a
+/// leonardo heap and then popping the elements back off again.
+fn smoothsort<T, O>(data: &mut [T], ops: &mut O)
+    where O: HeapOps<T>
+{
+    let layout = build_heap(data, ops);
+
+    sort_heap(data, layout, ops);
+}
+
+/// Rearranges a slice in place into a leonardo heap, with the largest element
+/// at the end, in linear time.
+pub fn heapify_slice<T: Ord>(data: &mut [T]) {
+    build_heap(data, &mut T::lt);
+}
+
+/// Rearranges a slice in place into a leonardo heap ordered by a comparator
+/// function, in linear time.
+///
+/// The element that the comparator considers largest will end up at the end of
+/// the slice.
+pub fn heapify_slice_by<T, F>(data: &mut [T], mut compare: F)
+    where F: FnMut(&T, &T) -> Ordering
+{
+    build_heap(data, &mut |a: &T, b: &T| compare(a, b) == Ordering::Less);
+}
+
+/// Checks that a slice is arranged as a leonardo heap, as by `heapify_slice`.
+///
+/// Returns the position and subheap order of the first violation of the heap
+/// or string property found.
+pub fn validate_slice<T: Ord>(data: &[T]) -> Result<(), InvariantError> {
+    let layout = layout::Layout::new_from_len(data.len());
+    validate::check_heap(data, &layout, &mut T::lt)
+}
+
+/// Checks that a slice is arranged as a leonardo heap ordered by a comparator
+/// function, as by `heapify_slice_by`.
+pub fn validate_slice_by<T, F>(
+    data: &[T], mut compare: F,
+) -> Result<(), InvariantError>
+    where F: FnMut(&T, &T) -> Ordering
+{
+    let layout = layout::Layout::new_from_len(data.len());
+    validate::check_heap(
+        data, &layout, &mut |a: &T, b: &T| compare(a, b) == Ordering::Less,
+    )
+}
+
+/// Sorts a slice in place in ascending order.
+///
+/// Runs in `O(n log n)` time in the worst case, degrading smoothly to `O(n)`
+/// as the input approaches being already sorted.  Does not allocate.  The sort
+/// is not stable.
+pub fn sort<T: Ord>(data: &mut [T]) {
+    smoothsort(data, &mut T::lt);
+}
+
+/// Sorts a slice in place using a comparator function.
+///
+/// The comparator must define a total ordering for the elements in the slice.
+/// See `sort` for details of the algorithm.
+pub fn sort_by<T, F>(data: &mut [T], mut compare: F)
+    where F: FnMut(&T, &T) -> Ordering
+{
+    smoothsort(data, &mut |a: &T, b: &T| compare(a, b) == Ordering::Less);
+}
+
+/// Sorts a slice in place by comparing the keys extracted from each element.
+///
+/// The key function is called twice for each comparison.  See `sort` for
+/// details of the algorithm.
+pub fn sort_by_key<T, K, F>(data: &mut [T], mut f: F)
+    where K: Ord, F: FnMut(&T) -> K
+{
+    smoothsort(data, &mut |a: &T, b: &T| f(a).lt(&f(b)));
+}
+
+/// Arranges a slice into a leonardo heap and then pops only the largest `k`
+/// elements, leaving them sorted at the end of the slice.
+fn partial_smoothsort<T, O>(data: &mut [T], k: usize, ops: &mut O)
+    where O: HeapOps<T>
+{
+    let k = cmp::min(k, data.len());
+    let mut layout = build_heap(data, ops);
+
+    sort_top(data, &mut layout, k, ops);
+}
+
+/// Partially sorts a slice in place so that its largest `k` elements are at
+/// the end of the slice, in ascending order.
+///
+/// The remaining elements are left in front of them in an unspecified order.
+/// Runs in `O(n + k log n)` time and does not allocate.  If `k` is greater
+/// than the length of the slice then the whole slice is sorted.
+pub fn partial_sort<T: Ord>(data: &mut [T], k: usize) {
+    partial_smoothsort(data, k, &mut T::lt);
+}
+
+/// Partially sorts a slice in place using a comparator function.
+///
+/// See `partial_sort` for details.
+pub fn partial_sort_by<T, F>(data: &mut [T], k: usize, mut compare: F)
+    where F: FnMut(&T, &T) -> Ordering
+{
+    partial_smoothsort(
+        data, k, &mut |a: &T, b: &T| compare(a, b) == Ordering::Less,
+    );
+}
+
+/// Partially sorts a slice in place by comparing the keys extracted from each
+/// element.
+///
+/// See `partial_sort` for details.
+pub fn partial_sort_by_key<T, K, F>(data: &mut [T], k: usize, mut f: F)
+    where K: Ord, F: FnMut(&T) -> K
+{
+    partial_smoothsort(data, k, &mut |a: &T, b: &T| f(a).lt(&f(b)));
+}
+
+/// Partially sorts a slice and returns its largest `k` elements, in ascending
+/// order.
+///
+/// The returned slice is the end of `data`.  See `partial_sort` for details.
+pub fn select_top_k<T: Ord>(data: &mut [T], k: usize) -> &mut [T] {
+    partial_sort(data, k);
+
+    let start = data.len() - cmp::min(k, data.len());
+    &mut data[start..]
 }
 
 
+/// A sorted iterator over the elements of a `LeonardoHeap`.
+///
+/// Created by `LeonardoHeap::iter`.  Lazily sorts the top elements of the heap
+/// in place as it is consumed.
 #[derive(Debug)]
-struct Iter<'a, T: 'a> {
+pub struct Iter<'a, T: 'a, C: 'a> {
     heap_data: &'a mut [T],
     layout: layout::Layout,
+    cmp: &'a C,
 }
 
 
-impl<'a, T : Ord + Debug> Iterator for Iter<'a, T>
+impl<'a, T, C> Iterator for Iter<'a, T, C>
+    where C: Compare<T>
 {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<&'a T> {
         self.layout.pop();
 
-        if self.heap_data.len() != 0 {
+        if !self.heap_data.is_empty() {
             // In order to avoid having more than one mutable reference to the
             // heap at any one time,we have to temporarily replace it in self
             // with a placeholder value.
-            let heap_data = std::mem::replace(&mut self.heap_data, &mut []);
+            let heap_data = mem::take(&mut self.heap_data);
 
             let (result, rest_data) = heap_data.split_last_mut().unwrap();
 
             // Store what's left of the heap back in self.
             self.heap_data = rest_data;
 
-            balance_after_pop(self.heap_data, &self.layout);
+            balance_after_pop(
+                self.heap_data, &self.layout, &mut is_less(self.cmp),
+            );
+            validate::paranoid_check(
+                self.heap_data, &self.layout, &mut is_less(self.cmp),
+            );
 
             Some(&*result)
         } else {
@@ -170,16 +627,73 @@ impl<'a, T : Ord + Debug> Iterator for Iter<'a, T>
 }
 
 
-impl<'a, T : Ord + Debug> ExactSizeIterator for Iter<'a, T> {}
+impl<'a, T, C> ExactSizeIterator for Iter<'a, T, C>
+    where C: Compare<T>
+{}
+
+
+impl<'a, T, C> FusedIterator for Iter<'a, T, C>
+    where C: Compare<T>
+{}
+
+
+/// An owning iterator over the elements of a `LeonardoHeap`, in no
+/// particular order.
+///
+/// Created by the `into_iter` method of `LeonardoHeap`.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct IntoIter<T> {
+    iter: vec::IntoIter<T>,
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back()
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+
+#[cfg(feature = "alloc")]
+impl<T> FusedIterator for IntoIter<T> {}
 
 
+/// An iterator that removes and returns elements from the top of a
+/// `LeonardoHeap`, in descending order.
+///
+/// Created by `LeonardoHeap::drain`.
 #[derive(Debug)]
-struct Drain<'a, T: 'a> {
-    heap: &'a mut LeonardoHeap<T>,
+pub struct Drain<
+    'a, T: 'a, C: 'a,
+    #[cfg(feature = "alloc")] S: 'a = Vec<T>,
+    #[cfg(not(feature = "alloc"))] S: 'a,
+> {
+    heap: &'a mut LeonardoHeap<T, C, S>,
 }
 
 
-impl<'a, T: Ord + Debug> Iterator for Drain<'a, T>
+impl<'a, T, C, S> Iterator for Drain<'a, T, C, S>
+    where C: Compare<T>, S: Storage<T>
 {
     type Item = T;
 
@@ -193,37 +707,256 @@ impl<'a, T: Ord + Debug> Iterator for Drain<'a, T>
 }
 
 
-impl<'a, T : Ord + Debug> ExactSizeIterator for Drain<'a, T> {}
+impl<'a, T, C, S> ExactSizeIterator for Drain<'a, T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{}
 
 
-#[derive(Debug)]
-pub struct LeonardoHeap<T> {
-    data: Vec<T>,
+impl<'a, T, C, S> FusedIterator for Drain<'a, T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{}
+
+
+/// An owning iterator over the elements of a `LeonardoHeap`, in descending
+/// order.
+///
+/// Created by `LeonardoHeap::into_sorted_iter`.  Elements are popped from the
+/// heap lazily as the iterator is consumed.
+#[derive(Clone, Debug)]
+pub struct IntoSortedIter<
+    T, C,
+    #[cfg(feature = "alloc")] S = Vec<T>,
+    #[cfg(not(feature = "alloc"))] S,
+> {
+    heap: LeonardoHeap<T, C, S>,
+}
+
+
+impl<T, C, S> Iterator for IntoSortedIter<T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.heap.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+
+impl<T, C, S> ExactSizeIterator for IntoSortedIter<T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{}
+
+
+impl<T, C, S> FusedIterator for IntoSortedIter<T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{}
+
+
+/// A guard wrapping a mutable reference to the largest element in a
+/// `LeonardoHeap`.
+///
+/// Created by `LeonardoHeap::peek_mut`.  If the element is modified then the
+/// heap will be rebalanced when the guard is dropped.
+///
+/// Leaking the guard, for example with `mem::forget`, after the element has
+/// been modified skips the rebalancing.  No elements are lost, but the heap
+/// is left out of order: `validate` will report an error, and elements may
+/// no longer be returned largest first.
+pub struct PeekMut<
+    'a, T, C,
+    #[cfg(feature = "alloc")] S = Vec<T>,
+    #[cfg(not(feature = "alloc"))] S,
+>
+    where T: 'a, C: 'a + Compare<T>, S: 'a + Storage<T>
+{
+    heap: &'a mut LeonardoHeap<T, C, S>,
+    sift: bool,
+}
+
+
+impl<'a, T, C, S> PeekMut<'a, T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{
+    /// Removes the peeked element from the heap and returns it.
+    pub fn pop(mut this: PeekMut<'a, T, C, S>) -> T {
+        // The element is being removed, so there is no point rebalancing when
+        // the guard is dropped.
+        this.sift = false;
+        this.heap.pop().unwrap()
+    }
+}
+
+
+impl<'a, T, C, S> Deref for PeekMut<'a, T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.heap.data.as_slice().last().unwrap()
+    }
+}
+
+
+impl<'a, T, C, S> DerefMut for PeekMut<'a, T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.sift = true;
+        self.heap.data.as_mut_slice().last_mut().unwrap()
+    }
+}
+
+
+impl<'a, T, C, S> Drop for PeekMut<'a, T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{
+    fn drop(&mut self) {
+        if self.sift {
+            // The top element is the head of the first subheap, so fixing it
+            // up is exactly the same as balancing after a push.
+            let heap = &mut *self.heap;
+            balance_after_push(
+                heap.data.as_mut_slice(), &heap.layout,
+                &mut is_less(&heap.cmp),
+            );
+            heap.paranoid_check();
+        }
+    }
+}
+
+
+impl<'a, T, C, S> fmt::Debug for PeekMut<'a, T, C, S>
+    where T: fmt::Debug, C: Compare<T>, S: Storage<T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PeekMut").field(&**self).finish()
+    }
+}
+
+
+/// A priority queue implemented as a leonardo heap.
+///
+/// Elements are ordered using a comparator, `C`.  By default this is
+/// `Natural`, which uses the elements' `Ord` implementation to give a
+/// max-heap.  See `MinLeonardoHeap` for a heap that yields the smallest
+/// element first.
+///
+/// Elements are kept in a `Storage`, `S`.  By default this is a `Vec`, but a
+/// heap can also be built on top of a fixed capacity buffer, such as an
+/// `ArrayStorage` or a `SliceStorage`, using `from_storage`.  Without the
+/// `alloc` feature there is no `Vec`, so the comparator and storage types have
+/// no defaults and must always be given.
+///
+/// Throughout this documentation the "largest" element is the greatest
+/// according to the comparator.
+#[derive(Clone, Debug)]
+pub struct LeonardoHeap<
+    T,
+    #[cfg(feature = "alloc")] C = Natural,
+    #[cfg(feature = "alloc")] S = Vec<T>,
+    #[cfg(not(feature = "alloc"))] C,
+    #[cfg(not(feature = "alloc"))] S,
+> {
+    data: S,
     layout: layout::Layout,
+    cmp: C,
+    marker: PhantomData<T>,
 }
 
 
-impl<T: Ord + Debug> LeonardoHeap<T> {
+#[cfg(feature = "alloc")]
+impl<T: Ord> LeonardoHeap<T> {
     /// Creates a new, empty `LeonardoHeap<T>`
     pub fn new() -> Self {
-        LeonardoHeap {
-            data: Vec::new(),
-            layout: layout::Layout::new(),
-        }
+        LeonardoHeap::new_by(Natural)
     }
 
     /// Creates a new `LeonardoHeap<T>` with space allocated for at least
     /// `capacity` elements.
     pub fn with_capacity(capacity: usize) -> Self {
-        LeonardoHeap {
-            data: Vec::with_capacity(capacity),
-            layout: layout::Layout::new(),
-        }
+        LeonardoHeap::with_capacity_by(capacity, Natural)
     }
 
-    /// Returns the number of elements for which space has been allocated.
-    pub fn capacity(&self) -> usize {
-        self.data.capacity()
+    /// Creates a new `LeonardoHeap<T>` containing the elements of `data`.
+    ///
+    /// The vector is rearranged in place into heap order in linear time,
+    /// without reallocating.
+    pub fn from_vec(data: Vec<T>) -> Self {
+        LeonardoHeap::from_vec_by(data, Natural)
+    }
+}
+
+
+impl<T: Ord, S: Storage<T>> LeonardoHeap<T, Natural, S> {
+    /// Creates a new heap that keeps its elements in `storage`.
+    ///
+    /// Any values already in the storage are rearranged in place into heap
+    /// order in linear time.
+    pub fn from_storage(storage: S) -> Self {
+        LeonardoHeap::from_storage_by(storage, Natural)
+    }
+}
+
+
+/// A leonardo heap that yields its smallest element first.
+#[cfg(feature = "alloc")]
+pub type MinLeonardoHeap<T> = LeonardoHeap<T, Reversed<Natural>>;
+
+
+#[cfg(feature = "alloc")]
+impl<T: Ord> MinLeonardoHeap<T> {
+    /// Creates a new, empty `MinLeonardoHeap<T>`.
+    pub fn new_min() -> Self {
+        LeonardoHeap::new_by(Reversed(Natural))
+    }
+
+    /// Creates a new `MinLeonardoHeap<T>` with space allocated for at least
+    /// `capacity` elements.
+    pub fn with_capacity_min(capacity: usize) -> Self {
+        LeonardoHeap::with_capacity_by(capacity, Reversed(Natural))
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T, K, F> LeonardoHeap<T, ByKey<F>>
+    where K: Ord, F: Fn(&T) -> K
+{
+    /// Creates a new, empty heap that orders its elements by the key returned
+    /// by `f`.
+    pub fn new_by_key(f: F) -> Self {
+        LeonardoHeap::new_by(ByKey::new(f))
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T, C: Compare<T>> LeonardoHeap<T, C> {
+    /// Creates a new, empty heap that orders its elements using the
+    /// comparator `cmp`.
+    pub fn new_by(cmp: C) -> Self {
+        LeonardoHeap::from_storage_by(Vec::new(), cmp)
+    }
+
+    /// Creates a new heap that orders its elements using the comparator `cmp`
+    /// with space allocated for at least `capacity` elements.
+    pub fn with_capacity_by(capacity: usize, cmp: C) -> Self {
+        LeonardoHeap::from_storage_by(Vec::with_capacity(capacity), cmp)
+    }
+
+    /// Creates a new heap containing the elements of `data`, ordered using the
+    /// comparator `cmp`.
+    ///
+    /// The vector is rearranged in place into heap order in linear time,
+    /// without reallocating.
+    pub fn from_vec_by(data: Vec<T>, cmp: C) -> Self {
+        LeonardoHeap::from_storage_by(data, cmp)
     }
 
     /// Reserve at least enough space for `additional` elements to be pushed
@@ -238,29 +971,116 @@ impl<T: Ord + Debug> LeonardoHeap<T> {
         self.data.reserve_exact(additional)
     }
 
+    /// Tries to reserve at least enough space for `additional` elements to be
+    /// pushed on to the heap, returning an error instead of aborting if the
+    /// allocation fails.
+    pub fn try_reserve(
+        &mut self, additional: usize,
+    ) -> Result<(), TryReserveError> {
+        self.data.try_reserve(additional)
+    }
+
+    /// Tries to reserve the minimum capacity for exactly `additional` elements
+    /// to be pushed onto the heap, returning an error instead of aborting if
+    /// the allocation fails.
+    pub fn try_reserve_exact(
+        &mut self, additional: usize,
+    ) -> Result<(), TryReserveError> {
+        self.data.try_reserve_exact(additional)
+    }
+
     /// Shrinks the capacity of the underlying storage to free up as much space
     /// as possible.
     pub fn shrink_to_fit(&mut self) {
         self.data.shrink_to_fit()
     }
 
-    /// Removes all elements from the heap that do not match a predicate.
-    pub fn retain<F>(&mut self, f: F)
-        where F: FnMut(&T) -> bool
-    {
-        // TODO there is a much more interesting implementation
-        self.data.retain(f);
+    /// Moves all of the elements of `other` into `self`, leaving `other`
+    /// empty.
+    ///
+    /// Rather than pushing each element individually, the new elements are
+    /// heapified from the bottom up in a single pass.  Subheaps made up
+    /// entirely of elements that were already in `self` are left alone, so
+    /// appending `m` elements to a heap of `n` costs roughly `O(m + log n)`.
+    pub fn append(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
 
-        self.heapify();
+        let dirty = self.data.len();
+        self.data.append(&mut other.data);
+        other.layout = layout::Layout::new();
+
+        self.reheapify_from(dirty);
     }
 
-    /// Removes all elements from the heap.
-    pub fn clear(&mut self) {
-        self.data.clear();
-        self.layout = layout::Layout::new();
+    /// Pushes an element on to the heap, returning an error instead of
+    /// aborting if space for it cannot be allocated.
+    ///
+    /// If the push fails the element is handed back along with the error.
+    pub fn try_push(&mut self, item: T) -> Result<(), (T, TryReserveError)> {
+        if let Err(error) = self.try_reserve(1) {
+            return Err((item, error));
+        }
+
+        self.push(item);
+        Ok(())
     }
 
-    /// Returns the number of elements in the heap.
+    /// Consumes the heap and returns a vector containing its elements sorted
+    /// in ascending order.
+    ///
+    /// The elements are sorted in place and the underlying storage is returned
+    /// without reallocating.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        self.sort();
+        self.data
+    }
+
+    /// Consumes the heap and returns the underlying storage, in heap order.
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+}
+
+
+impl<T, C, S> LeonardoHeap<T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{
+    /// Creates a new heap that keeps its elements in `storage`, ordered using
+    /// the comparator `cmp`.
+    ///
+    /// Any values already in the storage are rearranged in place into heap
+    /// order in linear time.
+    pub fn from_storage_by(storage: S, cmp: C) -> Self {
+        let mut heap = LeonardoHeap {
+            data: storage,
+            layout: layout::Layout::new(),
+            cmp,
+            marker: PhantomData,
+        };
+        heap.heapify();
+        heap
+    }
+
+    /// Returns a reference to the comparator used to order the heap.
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    /// Returns the number of elements that the heap can hold without
+    /// reallocating.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    /// Removes all elements from the heap.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.layout = layout::Layout::new();
+    }
+
+    /// Returns the number of elements in the heap.
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -270,50 +1090,127 @@ impl<T: Ord + Debug> LeonardoHeap<T> {
         self.data.is_empty()
     }
 
-    /// Removes duplicate elements from the heap, preserving heap order.
-    pub fn dedup(&mut self) {
-        self.sort();
-        self.data.dedup();
-        self.heapify();
-    }
-
     fn heapify(&mut self) {
-        let mut layout = layout::Layout::new();
-
-        // TODO harmless off-by-one error
-        for i in 0..self.data.len() {
-            balance_after_push(&mut self.data[0..i], &layout);
-            layout.push();
-        }
+        self.layout = build_heap(
+            self.data.as_mut_slice(), &mut is_less(&self.cmp),
+        );
+        self.paranoid_check();
     }
 
     /// Forces sorting of the entire underlying array.  The sorted array is
     /// still a valid leonardo heap.
     pub fn sort(&mut self) {
-        let mut layout = self.layout.clone();
-
-        // TODO harmless off-by-one error
-        for i in (0..self.data.len()).rev() {
-            layout.pop();
-            balance_after_pop(&mut self.data[0..i], &layout);
-        }
+        sort_heap(
+            self.data.as_mut_slice(), self.layout.clone(),
+            &mut is_less(&self.cmp),
+        );
+        self.paranoid_check();
     }
 
     /// Adds a new element to the heap.  The heap will be rebalanced to
     /// maintain the string and heap properties.
     ///
     /// Elements pushed more than once will not be deduplicated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the heap is backed by fixed capacity storage that is already
+    /// full.  Use `push_within_capacity` to handle this case.
     pub fn push(&mut self, item: T) {
-        self.data.push(item);
+        if self.data.push(item).is_err() {
+            panic!("heap storage is full");
+        }
         self.layout.push();
 
-        balance_after_push(self.data.as_mut_slice(), &self.layout);
+        balance_after_push(
+            self.data.as_mut_slice(), &self.layout, &mut is_less(&self.cmp),
+        );
+        self.paranoid_check();
+    }
+
+    /// Pushes an element on to the heap only if there is spare capacity for
+    /// it.  The heap never grows, and the element is handed back if it is
+    /// full.
+    ///
+    /// Together with `with_capacity`, or a fixed capacity storage, this allows
+    /// the heap to be used with a fixed amount of memory.
+    pub fn push_within_capacity(&mut self, item: T) -> Result<(), T> {
+        if self.data.len() == self.data.capacity() {
+            return Err(item);
+        }
+
+        self.push(item);
+        Ok(())
+    }
+
+    /// Pushes an element on to the heap and then pops and returns the largest
+    /// element.
+    ///
+    /// This is cheaper than a `push` followed by a `pop`.  If the new element
+    /// is at least as large as everything already in the heap it is returned
+    /// immediately.  Otherwise it replaces the top of the heap, which is then
+    /// rebalanced only once.
+    pub fn push_pop(&mut self, item: T) -> T {
+        let is_less = &mut is_less(&self.cmp);
+
+        match self.data.as_mut_slice().last_mut() {
+            Some(top) if is_less(&item, top) => {
+                let result = mem::replace(top, item);
+                balance_after_push(
+                    self.data.as_mut_slice(), &self.layout, is_less,
+                );
+                self.paranoid_check();
+                result
+            }
+            _ => item,
+        }
     }
 
     /// Returns a reference to the largest element in the heap without removing
     /// it.
     pub fn peek(&self) -> Option<&T> {
-        self.data.get(self.data.len())
+        self.data.as_slice().last()
+    }
+
+    /// Returns a slice containing the `n` largest elements in the heap, or all
+    /// of the elements if there are fewer than `n`.
+    ///
+    /// Only the top `n` elements are sorted, in place, in the same way as
+    /// `iter`.  They are moved to the end of the underlying storage, where
+    /// they must be kept in ascending order for the heap to remain valid, so
+    /// the largest element is the *last* in the slice.  Use `iter().rev()` on
+    /// the result to walk the elements from largest to smallest.
+    pub fn peek_n(&mut self, n: usize) -> &[T] {
+        let len = self.data.len();
+        let n = cmp::min(n, len);
+
+        // The popped values are left where they are, at the end of the
+        // storage, so the heap's own layout does not change.
+        sort_top(
+            self.data.as_mut_slice(), &mut self.layout.clone(), n,
+            &mut is_less(&self.cmp),
+        );
+        self.paranoid_check();
+
+        &self.data.as_slice()[len - n..]
+    }
+
+    /// Returns a guard giving mutable access to the largest element in the
+    /// heap, or `None` if the heap is empty.
+    ///
+    /// If the element is modified through the guard then the heap will be
+    /// rebalanced when the guard is dropped.  This is cheaper than popping the
+    /// element and pushing it back.  The guard must not be leaked after
+    /// modifying the element; see `PeekMut` for details.
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, C, S>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(PeekMut {
+                heap: self,
+                sift: false,
+            })
+        }
     }
 
     /// Removes and returns the largest element in the heap.  If the heap is
@@ -322,106 +1219,472 @@ impl<T: Ord + Debug> LeonardoHeap<T> {
         let result = self.data.pop();
         self.layout.pop();
 
-        balance_after_pop(self.data.as_mut_slice(), &self.layout);
+        balance_after_pop(
+            self.data.as_mut_slice(), &self.layout, &mut is_less(&self.cmp),
+        );
+        self.paranoid_check();
+
+        result
+    }
+
+    /// Consumes the heap and returns the underlying storage, in heap order.
+    pub fn into_storage(self) -> S {
+        self.data
+    }
+
+    /// Removes and returns the element at position `index` in the underlying
+    /// storage, as returned by `as_slice`.
+    ///
+    /// Only the subheap containing the element and the heads of the subheaps
+    /// after it need to be repaired, so this is about as cheap as a `pop`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove_at(&mut self, index: usize) -> T {
+        move_to_end(
+            self.data.as_mut_slice(), &self.layout, index,
+            &mut is_less(&self.cmp),
+        );
+
+        self.layout.pop();
+        let result = self.data.pop().unwrap();
+        self.paranoid_check();
 
         result
     }
 
+    /// Removes and returns the first element, in storage order, for which the
+    /// predicate returns `true`.  If no element matches, returns `None`.
+    ///
+    /// Finding the element requires a linear scan, but removing it is as cheap
+    /// as `remove_at`.
+    pub fn remove_first<F>(&mut self, f: F) -> Option<T>
+        where F: FnMut(&T) -> bool
+    {
+        let index = self.data.as_slice().iter().position(f)?;
+        Some(self.remove_at(index))
+    }
+
+    /// Removes all elements from the heap that do not match a predicate.
+    ///
+    /// Values in front of the first element to be removed keep their
+    /// positions, and any subheaps made up entirely of them are left alone.
+    /// Only the subheaps that were changed are rebuilt, so retaining on a
+    /// mostly sorted heap is close to linear with a small constant.
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&T) -> bool
+    {
+        // Compact the storage, remembering where the first value was removed.
+        // Rejected values are swapped towards the end and dropped together.
+        let mut kept = 0;
+        let mut dirty = None;
+        {
+            let data = self.data.as_mut_slice();
+            for index in 0..data.len() {
+                if f(&data[index]) {
+                    data.swap(kept, index);
+                    kept += 1;
+                } else if dirty.is_none() {
+                    dirty = Some(index);
+                }
+            }
+        }
+
+        if let Some(dirty) = dirty {
+            self.data.truncate(kept);
+            self.reheapify_from(dirty);
+        }
+    }
+
+    /// Removes duplicate elements from the heap, preserving heap order.
+    ///
+    /// Elements are considered duplicates if the comparator reports them as
+    /// equal.
+    pub fn dedup(&mut self) {
+        self.sort();
+
+        // Keep the first of each run of equal values, moving it down to follow
+        // the previous one kept.
+        let mut kept = 0;
+        {
+            let cmp = &self.cmp;
+            let data = self.data.as_mut_slice();
+            for index in 0..data.len() {
+                if kept == 0
+                    || cmp.compare(&data[index], &data[kept - 1])
+                        != Ordering::Equal
+                {
+                    data.swap(kept, index);
+                    kept += 1;
+                }
+            }
+        }
+        self.data.truncate(kept);
+
+        self.heapify();
+    }
+
+    /// Restores the heap after the values from `dirty` onwards have been
+    /// replaced, and the length of the storage possibly changed.
+    ///
+    /// Only the subheaps that contain replaced values are rebuilt.
+    fn reheapify_from(&mut self, dirty: usize) {
+        let previous = mem::replace(
+            &mut self.layout, layout::Layout::new_from_len(self.data.len()),
+        );
+        let ops = &mut is_less(&self.cmp);
+
+        for mut subheap in self.layout.iter(self.data.as_mut_slice()) {
+            reheapify_subheap(&mut subheap, dirty, &previous, ops);
+        }
+
+        // Top-level subheaps that are identical to ones in the previous layout
+        // are still in string order.
+        let mut end = 0;
+        let skip = self.layout.orders()
+            .zip(previous.orders())
+            .take_while(|&(order, previous_order)| {
+                end += leonardo(order);
+                order == previous_order && end <= dirty
+            })
+            .count();
+
+        restring_from(self.data.as_mut_slice(), &self.layout, skip, ops);
+
+        self.paranoid_check();
+    }
+
     /// Returns a *sorted* iterator over the elements in the heap.
     ///
     /// Will lazily sort the top elements of the heap in-place as it is
     /// consumed.
-    pub fn iter(
-        &mut self
-    ) -> impl Iterator<Item = &T> + ExactSizeIterator<Item = &T> {
+    pub fn iter(&mut self) -> Iter<'_, T, C> {
         Iter {
             heap_data: self.data.as_mut_slice(),
             layout: self.layout.clone(),
+            cmp: &self.cmp,
         }
     }
 
+    /// Returns an iterator over the elements in the heap in the order that
+    /// they are stored.
+    ///
+    /// Unlike `iter`, this does not need to modify the heap and so only
+    /// requires a shared reference.
+    pub fn iter_unordered(&self) -> slice::Iter<'_, T> {
+        self.data.as_slice().iter()
+    }
+
+    /// Returns a slice containing the underlying storage of the heap, in heap
+    /// order.
+    pub fn as_slice(&self) -> &[T] {
+        self.data.as_slice()
+    }
+
+    /// Checks that the heap property holds for every subheap, and that the
+    /// string property holds for the heap as a whole.
+    ///
+    /// This should always succeed.  It is intended for use in tests and debug
+    /// builds, for example after deserializing a heap.  Runs in linear time.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        validate::check_heap(
+            self.data.as_slice(), &self.layout, &mut is_less(&self.cmp),
+        )
+    }
+
+    /// Panics if the heap is invalid and the `paranoid` feature is enabled.
+    fn paranoid_check(&self) {
+        validate::paranoid_check(
+            self.data.as_slice(), &self.layout, &mut is_less(&self.cmp),
+        );
+    }
+
+    /// Returns a double-ended iterator over the elements in the heap in
+    /// ascending order.
+    ///
+    /// Unlike `iter`, yielding the smallest element first requires the whole
+    /// heap to be sorted up front.  The sort is performed in place and leaves
+    /// a valid heap, so no copy or reversal is needed.  Use `rev` to walk the
+    /// elements from largest to smallest.
+    pub fn iter_ascending(&mut self) -> slice::Iter<'_, T> {
+        self.sort();
+        self.data.as_slice().iter()
+    }
+
     /// Returns an iterator that removes and returns elements from the top of
     /// the heap.
-    pub fn drain<'a>(
-        &'a mut self
-    ) -> impl Iterator<Item = T> + ExactSizeIterator<Item = T> + 'a {
+    pub fn drain(&mut self) -> Drain<'_, T, C, S> {
         // TODO should drain clear the heap if not fully consumed
         Drain {
             heap: self,
         }
     }
+
+    /// Consumes the heap, returning an iterator that yields its elements in
+    /// descending order.
+    ///
+    /// Elements are popped lazily, so taking only the first few elements is
+    /// cheap.
+    pub fn into_sorted_iter(self) -> IntoSortedIter<T, C, S> {
+        IntoSortedIter {
+            heap: self,
+        }
+    }
+}
+
+
+impl<T, C, S> Default for LeonardoHeap<T, C, S>
+    where C: Compare<T> + Default, S: Storage<T> + Default
+{
+    /// Creates an empty heap using the default comparator and storage.
+    fn default() -> Self {
+        LeonardoHeap::from_storage_by(S::default(), C::default())
+    }
+}
+
+
+/// Two heaps are equal if they contain the same elements, regardless of how
+/// those elements are arranged in storage.
+///
+/// Elements are ordered using the comparator of `self`, and only then compared
+/// for equality, so this takes `O(n log n)` time and allocates.
+#[cfg(feature = "alloc")]
+impl<T, C, S> PartialEq for LeonardoHeap<T, C, S>
+    where T: PartialEq, C: Compare<T>, S: Storage<T>
+{
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        let cmp = &self.cmp;
+        let mut lhs: Vec<&T> = self.iter_unordered().collect();
+        let mut rhs: Vec<&T> = other.iter_unordered().collect();
+        lhs.sort_by(|a, b| cmp.compare(a, b));
+        rhs.sort_by(|a, b| cmp.compare(a, b));
+
+        let mut start = 0;
+        while start < lhs.len() {
+            // Find the run of elements that the comparator can not tell apart.
+            // If the heaps hold the same elements then the runs line up.
+            let end = start + lhs[start..].iter()
+                .take_while(|value| {
+                    cmp.compare(value, lhs[start]) == Ordering::Equal
+                })
+                .count();
+
+            if rhs[start..end].iter().any(|value| {
+                cmp.compare(value, lhs[start]) != Ordering::Equal
+            }) {
+                return false;
+            }
+
+            // Usually the two runs are already in the same order.
+            if lhs[start..end] == rhs[start..end] {
+                start = end;
+                continue;
+            }
+
+            // Elements within a run can be in any order, so match each one up
+            // with an equal element from the other run.
+            let mut matched = Vec::new();
+            matched.resize(end - start, false);
+            for value in &lhs[start..end] {
+                let found = rhs[start..end].iter()
+                    .zip(matched.iter_mut())
+                    .find(|&(other, ref used)| !**used && **other == **value);
+
+                match found {
+                    Some((_, used)) => *used = true,
+                    None => return false,
+                }
+            }
+
+            start = end;
+        }
+
+        true
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T, C, S> Eq for LeonardoHeap<T, C, S>
+    where T: Eq, C: Compare<T>, S: Storage<T>
+{}
+
+
+#[cfg(feature = "alloc")]
+impl<T, C: Compare<T> + Default> From<Vec<T>> for LeonardoHeap<T, C> {
+    /// Converts a vector into a heap in place, without reallocating.
+    fn from(data: Vec<T>) -> Self {
+        LeonardoHeap::from_vec_by(data, C::default())
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T, C> From<LeonardoHeap<T, C>> for Vec<T> {
+    /// Returns the underlying storage of the heap, in heap order.
+    fn from(heap: LeonardoHeap<T, C>) -> Self {
+        heap.data
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T, C> FromIterator<T> for LeonardoHeap<T, C>
+    where C: Compare<T> + Default
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        LeonardoHeap::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T, C: Compare<T>> Extend<T> for LeonardoHeap<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+
+        let (lower, _) = iter.size_hint();
+        self.reserve(lower);
+
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<'a, T, C> Extend<&'a T> for LeonardoHeap<T, C>
+    where T: 'a + Copy, C: Compare<T>
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T, C> IntoIterator for LeonardoHeap<T, C> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    /// Creates a consuming iterator that moves each element out of the heap
+    /// in arbitrary order.
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter {
+            iter: self.data.into_iter(),
+        }
+    }
+}
+
+
+impl<'a, T, C, S> IntoIterator for &'a mut LeonardoHeap<T, C, S>
+    where C: Compare<T>, S: Storage<T>
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, C>;
+
+    fn into_iter(self) -> Iter<'a, T, C> {
+        self.iter()
+    }
 }
 
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use rand;
     use rand::Rng;
 
+    use std::cell::Cell;
+    use std::cmp;
+    use std::cmp::Ordering;
+    use std::mem;
+    use std::mem::MaybeUninit;
+
     use layout;
     use subheap::SubHeapMut;
     use {LeonardoHeap, sift_down, balance_after_push, balance_after_pop};
+    use {sort, sort_by, sort_by_key, heapify_slice, heapify_slice_by};
+    use {partial_sort, partial_sort_by, partial_sort_by_key, select_top_k};
+    use {validate_slice, validate_slice_by, InvariantError};
+    use {Compare, ByKey, MinLeonardoHeap, PeekMut};
+    use {BoundedLeonardoHeap, IndexedLeonardoHeap};
+    use {Iter, Drain, IntoIter, IntoSortedIter};
+    use Natural;
+    use {Storage, ArrayStorage, SliceStorage};
+
+    /// Returns the integers from zero up to `len`, in a random order.
+    pub fn shuffled(len: i32) -> Vec<i32> {
+        let mut values: Vec<i32> = (0..len).collect();
+        rand::thread_rng().shuffle(values.as_mut_slice());
+        values
+    }
 
     #[test]
     fn test_sift_down_zero() {
         let mut subheap_data = [1];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 0));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 0), &mut i32::lt);
         assert_eq!(subheap_data, [1]);
     }
 
     #[test]
     fn test_sift_down_one() {
         let mut subheap_data = [1];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 1));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 1), &mut i32::lt);
         assert_eq!(subheap_data, [1]);
     }
 
     #[test]
     fn test_sift_down_two() {
         let mut subheap_data = [3, 2, 1];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2), &mut i32::lt);
         assert_eq!(subheap_data, [1, 2, 3]);
 
         let mut subheap_data = [3, 5, 4];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2), &mut i32::lt);
         assert_eq!(subheap_data, [3, 4, 5]);
 
         let mut subheap_data = [6, 7, 8];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2), &mut i32::lt);
         assert_eq!(subheap_data, [6, 7, 8]);
     }
 
     #[test]
     fn test_sift_down_three() {
         let mut subheap_data = [1, 2, 3, 4, 5];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3), &mut i32::lt);
         assert_eq!(subheap_data, [1, 2, 3, 4, 5]);
 
         let mut subheap_data = [1, 2, 3, 5, 4];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3), &mut i32::lt);
         assert_eq!(subheap_data, [1, 2, 3, 4, 5]);
 
         let mut subheap_data = [1, 2, 5, 4, 3];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3), &mut i32::lt);
         assert_eq!(subheap_data, [1, 2, 3, 4, 5]);
 
         let mut subheap_data = [2, 3, 5, 4, 1];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3), &mut i32::lt);
         assert_eq!(subheap_data, [2, 1, 3, 4, 5]);
 
         let mut subheap_data = [3, 2, 5, 4, 1];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3), &mut i32::lt);
         assert_eq!(subheap_data, [1, 2, 3, 4, 5]);
     }
 
     #[test]
     fn test_sift_down_sorting() {
         let mut subheap_data = [5, 5, 4];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 2), &mut i32::lt);
         assert_eq!(subheap_data, [4, 5, 5]);
 
         let mut subheap_data = [1, 2, 4, 4, 3];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 3), &mut i32::lt);
         assert_eq!(subheap_data, [1, 2, 3, 4, 4]);
     }
 
@@ -429,14 +1692,14 @@ mod tests {
     #[should_panic]
     fn test_sift_down_wrong_order() {
         let mut subheap_data : [i32; 0] = [];
-        sift_down(&mut SubHeapMut::new(&mut subheap_data, 0));
+        sift_down(&mut SubHeapMut::new(&mut subheap_data, 0), &mut i32::lt);
     }
 
     #[test]
     fn test_balance_after_push_first() {
         let mut subheap_data = [1];
         balance_after_push(
-            &mut subheap_data, &layout::Layout::new_from_len(1),
+            &mut subheap_data, &layout::Layout::new_from_len(1), &mut i32::lt,
         );
         assert_eq!(subheap_data, [1]);
     }
@@ -445,13 +1708,13 @@ mod tests {
     fn test_balance_after_push_second() {
         let mut subheap_data = [1, 2];
         balance_after_push(
-            &mut subheap_data, &layout::Layout::new_from_len(2),
+            &mut subheap_data, &layout::Layout::new_from_len(2), &mut i32::lt,
         );
         assert_eq!(subheap_data, [1, 2]);
 
         let mut subheap_data = [2, 1];
         balance_after_push(
-            &mut subheap_data, &layout::Layout::new_from_len(2),
+            &mut subheap_data, &layout::Layout::new_from_len(2), &mut i32::lt,
         );
         assert_eq!(subheap_data, [1, 2]);
     }
@@ -460,13 +1723,13 @@ mod tests {
     fn test_balance_after_push_merge() {
         let mut subheap_data = [1, 2, 3];
         balance_after_push(
-            &mut subheap_data, &layout::Layout::new_from_len(3),
+            &mut subheap_data, &layout::Layout::new_from_len(3), &mut i32::lt,
         );
         assert_eq!(subheap_data, [1, 2, 3]);
 
         let mut subheap_data = [1, 3, 2];
         balance_after_push(
-            &mut subheap_data, &layout::Layout::new_from_len(3),
+            &mut subheap_data, &layout::Layout::new_from_len(3), &mut i32::lt,
         );
         assert_eq!(subheap_data, [1, 2, 3]);
     }
@@ -476,59 +1739,79 @@ mod tests {
     fn test_balance_after_push_mismatched_lengths() {
         let mut subheap_data = [1, 2, 3, 4];
         balance_after_push(
-            &mut subheap_data, &layout::Layout::new_from_len(12),
+            &mut subheap_data, &layout::Layout::new_from_len(12), &mut i32::lt,
         );
     }
 
     #[test]
     fn test_balance_after_pop_empty() {
         let mut subheap_data : [i32; 0]= [];
-        balance_after_pop(&mut subheap_data, &layout::Layout::new_from_len(0));
+        balance_after_pop(
+            &mut subheap_data, &layout::Layout::new_from_len(0), &mut i32::lt,
+        );
         assert_eq!(subheap_data, []);
     }
 
     #[test]
     fn test_balance_after_pop_one() {
         let mut heap_data = [1];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(1));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(1), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1]);
     }
 
     #[test]
     fn test_balance_after_pop_two() {
         let mut heap_data = [1, 2];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(2));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(2), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2]);
 
         let mut heap_data = [2, 1];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(2));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(2), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2]);
     }
 
     #[test]
     fn test_balance_after_pop_split_heaps() {
         let mut heap_data = [1, 2, 3, 4, 5, 6, 7];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(7));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(7), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2, 3, 4, 5, 6, 7]);
 
         let mut heap_data = [1, 2, 3, 4, 5, 7, 6];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(7));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(7), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2, 3, 4, 5, 6, 7]);
 
         let mut heap_data = [1, 2, 3, 4, 6, 5, 7];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(7));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(7), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2, 3, 4, 5, 6, 7]);
 
         let mut heap_data = [1, 2, 3, 4, 7, 5, 6];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(7));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(7), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2, 3, 4, 5, 6, 7]);
 
         let mut heap_data = [1, 2, 3, 4, 6, 7, 5];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(7));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(7), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2, 3, 4, 5, 6, 7]);
 
         let mut heap_data = [1, 2, 3, 4, 7, 6, 5];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(7));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(7), &mut i32::lt,
+        );
         assert_eq!(heap_data, [1, 2, 3, 4, 5, 6, 7]);
     }
 
@@ -539,7 +1822,9 @@ mod tests {
             9, 7, 13,
             8
         ];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(13));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(13), &mut i32::lt,
+        );
         assert_eq!(heap_data, [
             1, 2, 3, 4, 5, 6, 9, 10, 11,
             8, 7, 12,
@@ -554,7 +1839,9 @@ mod tests {
             4,
             8,
         ];
-        balance_after_pop(&mut heap_data, &layout::Layout::new_from_len(11));
+        balance_after_pop(
+            &mut heap_data, &layout::Layout::new_from_len(11), &mut i32::lt,
+        );
         assert_eq!(heap_data, [
             3, 0, 4, 1, 5, 2, 6, 7, 8,
             9,
@@ -567,7 +1854,7 @@ mod tests {
     fn test_balance_after_pop_mismatched_lengths() {
         let mut subheap_data = [1, 2, 3, 4];
         balance_after_pop(
-            &mut subheap_data, &layout::Layout::new_from_len(12),
+            &mut subheap_data, &layout::Layout::new_from_len(12), &mut i32::lt,
         );
     }
 
@@ -598,7 +1885,7 @@ mod tests {
 
         let mut heap = LeonardoHeap::new();
         for input in inputs {
-            heap.push(input.clone());
+            heap.push(input);
         }
 
         let mut outputs: Vec<i32> = Vec::new();
@@ -622,7 +1909,7 @@ mod tests {
 
         let mut heap = LeonardoHeap::new();
         for input in &inputs {
-            heap.push(input.clone());
+            heap.push(*input);
         }
 
         heap.sort();
@@ -649,4 +1936,1061 @@ mod tests {
         var = 1;
         assert_eq!(heap_iter.next(), Some(&var));
     }
+
+    #[test]
+    fn test_sort_slice_empty() {
+        let mut data : [i32; 0] = [];
+        sort(&mut data);
+        assert_eq!(data, []);
+    }
+
+    #[test]
+    fn test_sort_slice_random() {
+        let mut inputs = shuffled(200);
+        let expected: Vec<i32> = (0..200).collect();
+
+        sort(inputs.as_mut_slice());
+
+        assert_eq!(inputs, expected);
+    }
+
+    #[test]
+    fn test_sort_slice_duplicates() {
+        let mut data = [3, 1, 2, 3, 1, 2, 3, 1, 2, 0];
+        sort(&mut data);
+        assert_eq!(data, [0, 1, 1, 1, 2, 2, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn test_sort_by_slice_reverse() {
+        let mut inputs = shuffled(200);
+        let expected: Vec<i32> = (0..200).rev().collect();
+
+        sort_by(inputs.as_mut_slice(), |a, b| b.cmp(a));
+
+        assert_eq!(inputs, expected);
+    }
+
+    #[test]
+    fn test_sort_by_key_slice() {
+        let mut data = [(3, 'a'), (1, 'b'), (2, 'c'), (0, 'd')];
+        sort_by_key(&mut data, |&(_, c)| c);
+        assert_eq!(data, [(3, 'a'), (1, 'b'), (2, 'c'), (0, 'd')]);
+
+        sort_by_key(&mut data, |&(n, _)| n);
+        assert_eq!(data, [(0, 'd'), (1, 'b'), (2, 'c'), (3, 'a')]);
+    }
+
+    #[test]
+    fn test_new_by_closure() {
+        let mut heap = LeonardoHeap::new_by(|a: &i32, b: &i32| b.cmp(a));
+        heap.push(4);
+        heap.push(1);
+        heap.push(2);
+        heap.push(3);
+
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(2));
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(4));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_new_by_key_random() {
+        let inputs: Vec<(i32, i32)> = shuffled(200).into_iter()
+            .map(|i| (i, -i))
+            .collect();
+
+        let mut heap = LeonardoHeap::new_by_key(|&(_, key): &(i32, i32)| key);
+        for input in inputs {
+            heap.push(input);
+        }
+
+        // The largest key belongs to the smallest value.
+        let expected: Vec<(i32, i32)> = (0..200).map(|i| (i, -i)).collect();
+        let outputs: Vec<(i32, i32)> = heap.drain().collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_by_key_compare() {
+        let cmp = ByKey::new(|s: &&str| s.len());
+        assert_eq!(cmp.compare(&"aaa", &"bb"), Ordering::Greater);
+        assert_eq!(cmp.compare(&"aa", &"bb"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_min_heap_push_pop() {
+        let mut heap = MinLeonardoHeap::new_min();
+        heap.push(4);
+        heap.push(1);
+        heap.push(2);
+        heap.push(3);
+
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(2));
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(4));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_min_heap_iter_drain_random() {
+        let inputs = shuffled(200);
+        let expected: Vec<i32> = (0..200).collect();
+
+        let mut heap = LeonardoHeap::with_capacity_min(inputs.len());
+        for input in inputs {
+            heap.push(input);
+        }
+
+        let iterated: Vec<i32> = heap.iter().cloned().collect();
+        assert_eq!(iterated, expected);
+
+        let drained: Vec<i32> = heap.drain().collect();
+        assert_eq!(drained, expected);
+    }
+
+    #[test]
+    fn test_peek_mut_empty() {
+        let mut heap: LeonardoHeap<i32> = LeonardoHeap::new();
+        assert!(heap.peek_mut().is_none());
+    }
+
+    #[test]
+    fn test_peek_mut_decrease() {
+        let mut heap = LeonardoHeap::new();
+        heap.push(4);
+        heap.push(1);
+        heap.push(2);
+        heap.push(3);
+
+        {
+            let mut top = heap.peek_mut().unwrap();
+            assert_eq!(*top, 4);
+            *top = 0;
+        }
+
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(2));
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(0));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_peek_mut_pop() {
+        let mut heap = LeonardoHeap::new();
+        heap.push(4);
+        heap.push(1);
+        heap.push(2);
+        heap.push(3);
+
+        {
+            let mut top = heap.peek_mut().unwrap();
+            *top = 5;
+            assert_eq!(PeekMut::pop(top), 5);
+        }
+
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.pop(), Some(2));
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_peek_mut_leak() {
+        let mut heap: LeonardoHeap<i32> = (0..20).collect();
+
+        {
+            let mut top = heap.peek_mut().unwrap();
+            *top = -1;
+            mem::forget(top);
+        }
+
+        // The heap is out of order, but still holds every element.
+        assert!(heap.validate().is_err());
+
+        let mut outputs = heap.into_vec();
+        outputs.sort();
+        let expected: Vec<i32> = (-1..19).collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_peek_mut_random() {
+        let mut rng = rand::thread_rng();
+
+        let mut heap = LeonardoHeap::new();
+        let mut expected = Vec::new();
+        for _ in 0..200 {
+            let value = rng.gen_range(0, 1000);
+            heap.push(value);
+            expected.push(value);
+        }
+
+        for _ in 0..200 {
+            let value = rng.gen_range(0, 1000);
+
+            let mut top = heap.peek_mut().unwrap();
+
+            let max = expected.iter().cloned().max().unwrap();
+            assert_eq!(*top, max);
+            let position = expected.iter().position(|&v| v == max).unwrap();
+            expected[position] = value;
+
+            *top = value;
+        }
+
+        expected.sort_by(|a, b| b.cmp(a));
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut heap: LeonardoHeap<i32> = (0..20).collect();
+        heap.retain(|&x| x % 2 == 0);
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, vec![18, 16, 14, 12, 10, 8, 6, 4, 2, 0]);
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut heap: LeonardoHeap<i32> = vec![3, 1, 2, 3, 1, 2].into();
+        heap.dedup();
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_from_vec_random() {
+        let inputs = shuffled(200);
+        let expected: Vec<i32> = (0..200).rev().collect();
+
+        let mut heap = LeonardoHeap::<i32>::from(inputs);
+        assert_eq!(heap.len(), 200);
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_from_iter_extend() {
+        let mut heap: MinLeonardoHeap<i32> = vec![5, 3, 7].into_iter()
+            .collect();
+        heap.extend(vec![4, 1]);
+        heap.extend(&[6, 2]);
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let heap: LeonardoHeap<i32> = (0..10).collect();
+
+        let mut outputs: Vec<i32> = heap.into_iter().collect();
+        outputs.sort();
+        assert_eq!(outputs, (0..10).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_into_iter_mut_ref() {
+        let mut heap: LeonardoHeap<i32> = vec![2, 4, 1, 3].into();
+
+        let mut outputs = Vec::new();
+        for value in &mut heap {
+            outputs.push(*value);
+        }
+        assert_eq!(outputs, vec![4, 3, 2, 1]);
+        assert_eq!(heap.len(), 4);
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let mut heap = LeonardoHeap::new();
+        heap.push(2);
+        heap.push(1);
+        heap.sort();
+
+        let data: Vec<i32> = heap.into();
+        assert_eq!(data, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_clone_eq_default() {
+        let mut heap: LeonardoHeap<i32> = Default::default();
+        assert_eq!(heap, LeonardoHeap::new_by(Natural));
+
+        heap.push(1);
+        heap.push(2);
+
+        let cloned = heap.clone();
+        assert_eq!(heap, cloned);
+
+        heap.push(3);
+        assert!(heap != cloned);
+    }
+
+    #[test]
+    fn test_eq_ignores_arrangement() {
+        let heapified: LeonardoHeap<i32> = vec![1, 2, 3].into();
+
+        let mut pushed = LeonardoHeap::new();
+        for value in [3, 2, 1] {
+            pushed.push(value);
+        }
+
+        assert!(heapified.as_slice() != pushed.as_slice());
+        assert_eq!(heapified, pushed);
+
+        pushed.pop();
+        pushed.push(4);
+        assert!(heapified != pushed);
+    }
+
+    #[test]
+    fn test_eq_ties_under_comparator() {
+        fn key(value: &(i32, char)) -> i32 {
+            value.0
+        }
+
+        let lhs = LeonardoHeap::from_vec_by(
+            vec![(1, 'a'), (1, 'b'), (0, 'c')], ByKey::new(key),
+        );
+        let rhs = LeonardoHeap::from_vec_by(
+            vec![(1, 'b'), (0, 'c'), (1, 'a')], ByKey::new(key),
+        );
+        assert!(lhs == rhs);
+
+        let rhs = LeonardoHeap::from_vec_by(
+            vec![(1, 'b'), (0, 'c'), (1, 'b')], ByKey::new(key),
+        );
+        assert!(lhs != rhs);
+    }
+
+    #[test]
+    fn test_eq_many_duplicates() {
+        // Too slow to finish if every element has to be matched up by hand.
+        let lhs: LeonardoHeap<u32> = vec![7; 100000].into();
+        let rhs: LeonardoHeap<u32> = vec![7; 100000].into();
+        assert_eq!(lhs, rhs);
+
+        let mut values = vec![7; 100000];
+        values[500] = 8;
+        let rhs: LeonardoHeap<u32> = values.into();
+        assert!(lhs != rhs);
+    }
+
+    #[test]
+    fn test_heapify_slice_max_at_end() {
+        let mut data = shuffled(200);
+
+        heapify_slice(data.as_mut_slice());
+        assert_eq!(data.last(), Some(&199));
+
+        heapify_slice_by(data.as_mut_slice(), |a, b| b.cmp(a));
+        assert_eq!(data.last(), Some(&0));
+    }
+
+    #[test]
+    fn test_heapify_slice_comparisons_linear() {
+        for &len in &[10, 100, 1000, 10000, 100000] {
+            let mut data = shuffled(len);
+
+            let mut comparisons = 0;
+            heapify_slice_by(data.as_mut_slice(), |a, b| {
+                comparisons += 1;
+                a.cmp(b)
+            });
+
+            assert!(comparisons <= 3 * len, "{} > 3 * {}", comparisons, len);
+        }
+    }
+
+    #[test]
+    fn test_from_vec_sorted_input() {
+        let inputs : Vec<i32> = (0..200).collect();
+
+        let mut heap = LeonardoHeap::from_vec(inputs.clone());
+        assert_eq!(heap.data, inputs);
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, inputs.into_iter().rev().collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_push_pop_replace() {
+        let mut heap: LeonardoHeap<i32> = LeonardoHeap::new();
+        assert_eq!(heap.push_pop(3), 3);
+        assert!(heap.is_empty());
+
+        heap.push(4);
+        heap.push(1);
+        heap.push(2);
+
+        assert_eq!(heap.push_pop(5), 5);
+        assert_eq!(heap.push_pop(4), 4);
+        assert_eq!(heap.push_pop(3), 4);
+        assert_eq!(heap.push_pop(0), 3);
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_push_pop_random() {
+        let mut rng = rand::thread_rng();
+
+        let mut heap = LeonardoHeap::new();
+        let mut expected = Vec::new();
+        for _ in 0..100 {
+            let value = rng.gen_range(0, 1000);
+            heap.push(value);
+            expected.push(value);
+        }
+
+        for _ in 0..200 {
+            let value = rng.gen_range(0, 1000);
+
+            expected.push(value);
+            expected.sort();
+
+            assert_eq!(Some(heap.push_pop(value)), expected.pop());
+        }
+
+        expected.reverse();
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_append_empty() {
+        let mut heap: LeonardoHeap<i32> = vec![1, 2, 3].into();
+        let mut other = LeonardoHeap::new();
+
+        heap.append(&mut other);
+        assert_eq!(heap.len(), 3);
+
+        other.append(&mut heap);
+        assert_eq!(other.len(), 3);
+        assert!(heap.is_empty());
+
+        let outputs: Vec<i32> = other.drain().collect();
+        assert_eq!(outputs, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_append_matches_push() {
+        let mut rng = rand::thread_rng();
+
+        let lens = [(1, 1), (13, 200), (200, 13), (150, 150)];
+        for &(fst_len, snd_len) in &lens {
+            let fst_inputs : Vec<i32> = (0..fst_len)
+                .map(|_| rng.gen_range(0, 100))
+                .collect();
+            let snd_inputs : Vec<i32> = (0..snd_len)
+                .map(|_| rng.gen_range(0, 100))
+                .collect();
+
+            let mut expected = LeonardoHeap::new();
+            for &input in fst_inputs.iter().chain(snd_inputs.iter()) {
+                expected.push(input);
+            }
+
+            let mut heap: LeonardoHeap<i32> = fst_inputs.into();
+            let mut other: LeonardoHeap<i32> = snd_inputs.into();
+            heap.append(&mut other);
+
+            assert!(other.is_empty());
+            assert_eq!(other.pop(), None);
+            assert_eq!(heap.len(), expected.len());
+
+            let outputs: Vec<i32> = heap.drain().collect();
+            let expected: Vec<i32> = expected.drain().collect();
+            assert_eq!(outputs, expected);
+        }
+    }
+
+    #[test]
+    fn test_append_small_to_large() {
+        let comparisons = Cell::new(0);
+        let cmp = |a: &i32, b: &i32| {
+            comparisons.set(comparisons.get() + 1);
+            a.cmp(b)
+        };
+
+        let mut heap = LeonardoHeap::from_vec_by((0..10000).collect(), cmp);
+        let before = heap.as_slice().to_vec();
+        let mut other = LeonardoHeap::from_vec_by(vec![5000, -1, 20000], cmp);
+
+        comparisons.set(0);
+        heap.append(&mut other);
+
+        // Rebuilding the whole heap would need at least one comparison for
+        // every element.  Paranoid mode checks every element as well.
+        if !cfg!(feature = "paranoid") {
+            assert!(comparisons.get() < 1000);
+        }
+        assert_eq!(heap.validate(), Ok(()));
+
+        // The largest subheap, of order 18, is unchanged.
+        assert_eq!(&heap.as_slice()[..8361], &before[..8361]);
+
+        let outputs: Vec<i32> = heap.drain().take(2).collect();
+        assert_eq!(outputs, vec![20000, 9999]);
+    }
+
+    #[test]
+    fn test_into_sorted_iter() {
+        let heap: LeonardoHeap<i32> = vec![2, 4, 1, 3].into();
+
+        let mut iter = heap.into_sorted_iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next(), Some(4));
+        assert_eq!(iter.len(), 3);
+
+        let rest: Vec<i32> = iter.by_ref().collect();
+        assert_eq!(rest, vec![3, 2, 1]);
+
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iterators_fused() {
+        let mut heap: LeonardoHeap<i32> = vec![1].into();
+
+        {
+            let mut iter = heap.iter();
+            assert_eq!(iter.next(), Some(&1));
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next(), None);
+        }
+
+        {
+            let mut drain = heap.drain();
+            assert_eq!(drain.next(), Some(1));
+            assert_eq!(drain.next(), None);
+            assert_eq!(drain.next(), None);
+        }
+
+        let mut into_iter = heap.into_iter();
+        assert_eq!(into_iter.next(), None);
+        assert_eq!(into_iter.next(), None);
+    }
+
+    #[test]
+    fn test_iterators_nameable() {
+        struct Iterators<'a> {
+            iter: Iter<'a, i32, Natural>,
+        }
+
+        fn sum_drain(drain: Drain<i32, Natural>) -> i32 {
+            drain.sum()
+        }
+
+        fn first(mut iter: IntoSortedIter<i32, Natural>) -> Option<i32> {
+            iter.next()
+        }
+
+        fn last(iter: IntoIter<i32>) -> Option<i32> {
+            iter.max()
+        }
+
+        let mut heap: LeonardoHeap<i32> = vec![2, 4, 1, 3].into();
+        {
+            let mut iterators = Iterators { iter: heap.iter() };
+            assert_eq!(iterators.iter.next(), Some(&4));
+        }
+
+        assert_eq!(first(heap.clone().into_sorted_iter()), Some(4));
+        assert_eq!(last(heap.clone().into_iter()), Some(4));
+        assert_eq!(sum_drain(heap.drain()), 10);
+    }
+
+    #[test]
+    fn test_into_sorted_vec() {
+        let inputs = shuffled(200);
+        let expected: Vec<i32> = (0..200).collect();
+
+        let heap: LeonardoHeap<i32> = inputs.into();
+        let capacity = heap.capacity();
+
+        let outputs = heap.into_sorted_vec();
+        assert_eq!(outputs, expected);
+        assert_eq!(outputs.capacity(), capacity);
+
+        let heap = MinLeonardoHeap::from_vec_by(
+            vec![2, 3, 1], Default::default(),
+        );
+        assert_eq!(heap.into_sorted_vec(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_into_vec_heap_order() {
+        let mut heap = LeonardoHeap::new();
+        heap.push(1);
+        heap.push(2);
+
+        let data = heap.clone().into_vec();
+        assert_eq!(data, heap.data);
+
+        let mut rebuilt = LeonardoHeap::from_vec(data);
+        assert_eq!(rebuilt.pop(), Some(2));
+        assert_eq!(rebuilt.pop(), Some(1));
+    }
+
+    #[test]
+    fn test_iter_ascending() {
+        let inputs = shuffled(200);
+        let expected: Vec<i32> = (0..200).collect();
+
+        let mut heap: LeonardoHeap<i32> = inputs.into();
+
+        let ascending: Vec<i32> = heap.iter_ascending().cloned().collect();
+        assert_eq!(ascending, expected);
+
+        let descending: Vec<i32> = heap.iter_ascending().rev().cloned()
+            .collect();
+        assert_eq!(descending, expected.iter().rev().cloned()
+            .collect::<Vec<i32>>());
+
+        {
+            let mut iter = heap.iter_ascending();
+            assert_eq!(iter.next(), Some(&0));
+            assert_eq!(iter.next_back(), Some(&199));
+            assert_eq!(iter.len(), 198);
+        }
+
+        // The heap should still be usable afterwards.
+        heap.push(100);
+        assert_eq!(heap.pop(), Some(199));
+        assert_eq!(heap.len(), 200);
+    }
+
+    #[test]
+    fn test_iter_unordered_as_slice() {
+        let mut heap = LeonardoHeap::new();
+        heap.push(4);
+        heap.push(1);
+        heap.push(2);
+        heap.push(3);
+
+        let shared = &heap;
+
+        assert_eq!(shared.as_slice().len(), 4);
+        assert_eq!(shared.as_slice().last(), Some(&4));
+
+        let mut contents: Vec<i32> = shared.iter_unordered().cloned()
+            .collect();
+        assert_eq!(contents.as_slice(), shared.as_slice());
+
+        contents.sort();
+        assert_eq!(contents, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_remove_at_every_index() {
+        for len in 1..40 {
+            for index in 0..len {
+                let mut heap: LeonardoHeap<i32> = (0..len as i32).collect();
+
+                let removed = heap.remove_at(index);
+                assert_eq!(heap.len(), len - 1);
+
+                let mut expected: Vec<i32> = (0..len as i32)
+                    .filter(|&value| value != removed)
+                    .collect();
+                expected.reverse();
+
+                let outputs: Vec<i32> = heap.drain().collect();
+                assert_eq!(outputs, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_at_random() {
+        let mut rng = rand::thread_rng();
+
+        let mut heap: LeonardoHeap<i32> = shuffled(200).into();
+        let mut expected: Vec<i32> = (0..200).collect();
+
+        for _ in 0..100 {
+            let index = rng.gen_range(0, heap.len());
+            let removed = heap.remove_at(index);
+
+            let position = expected.iter()
+                .position(|&value| value == removed)
+                .unwrap();
+            expected.remove(position);
+
+            heap.push(removed + 1000);
+            expected.push(removed + 1000);
+        }
+
+        expected.sort();
+        expected.reverse();
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_remove_at_out_of_bounds() {
+        let mut heap: LeonardoHeap<i32> = vec![1, 2, 3].into();
+        heap.remove_at(3);
+    }
+
+    #[test]
+    fn test_remove_first() {
+        let mut heap: LeonardoHeap<i32> = (0..10).collect();
+
+        assert_eq!(heap.remove_first(|&value| value == 4), Some(4));
+        assert_eq!(heap.remove_first(|&value| value == 4), None);
+        assert_eq!(heap.remove_first(|&value| value > 8), Some(9));
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, vec![8, 7, 6, 5, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_retain_random() {
+        let mut rng = rand::thread_rng();
+
+        for len in 0..100 {
+            let mut heap: LeonardoHeap<i32> = shuffled(len).into();
+
+            // Pop a few values so that the layout is not always the one that
+            // would be built from scratch.
+            for _ in 0..rng.gen_range(0, 3) {
+                heap.pop();
+            }
+
+            let modulus = rng.gen_range(2, 6);
+            let mut expected: Vec<i32> = heap.iter_unordered()
+                .cloned()
+                .filter(|value| value % modulus != 0)
+                .collect();
+            expected.sort_by(|a, b| b.cmp(a));
+
+            heap.retain(|value| value % modulus != 0);
+
+            let outputs: Vec<i32> = heap.drain().collect();
+            assert_eq!(outputs, expected);
+        }
+    }
+
+    #[test]
+    fn test_retain_keeps_sorted_prefix() {
+        let mut heap: LeonardoHeap<i32> = (0..100).collect();
+        heap.sort();
+
+        heap.retain(|&value| value != 99);
+        let expected: Vec<i32> = (0..99).collect();
+        assert_eq!(heap.as_slice(), expected.as_slice());
+
+        heap.retain(|&value| value != 50);
+        assert_eq!(heap.as_slice()[0..50], expected[0..50]);
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        let expected: Vec<i32> = (0..99).rev().filter(|&v| v != 50).collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_try_reserve() {
+        let mut heap: LeonardoHeap<i32> = LeonardoHeap::new();
+
+        assert!(heap.try_reserve(10).is_ok());
+        assert!(heap.capacity() >= 10);
+
+        assert!(heap.try_reserve_exact(20).is_ok());
+        assert!(heap.capacity() >= 20);
+
+        assert!(heap.try_reserve(usize::MAX).is_err());
+        assert!(heap.try_reserve_exact(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_try_push() {
+        let mut heap: LeonardoHeap<i32> = LeonardoHeap::new();
+
+        for value in &[3, 1, 4, 1, 5] {
+            assert!(heap.try_push(*value).is_ok());
+        }
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, vec![5, 4, 3, 1, 1]);
+    }
+
+    #[test]
+    fn test_push_within_capacity() {
+        let mut heap: LeonardoHeap<i32> = LeonardoHeap::with_capacity(4);
+        let capacity = heap.capacity();
+
+        for value in 0..capacity as i32 {
+            assert_eq!(heap.push_within_capacity(value), Ok(()));
+        }
+        assert_eq!(heap.push_within_capacity(-1), Err(-1));
+        assert_eq!(heap.capacity(), capacity);
+
+        assert_eq!(heap.pop(), Some(capacity as i32 - 1));
+        assert_eq!(heap.push_within_capacity(-1), Ok(()));
+        assert_eq!(heap.len(), capacity);
+    }
+
+    #[test]
+    fn test_array_storage_heap() {
+        let inputs = shuffled(100);
+
+        let mut heap = LeonardoHeap::from_storage(
+            ArrayStorage::<i32, 100>::new(),
+        );
+        for input in inputs {
+            heap.push(input);
+        }
+        assert_eq!(heap.len(), 100);
+        assert_eq!(heap.capacity(), 100);
+        assert_eq!(heap.push_within_capacity(100), Err(100));
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        let expected: Vec<i32> = (0..100).rev().collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_slice_storage_heap() {
+        let mut buffer = [MaybeUninit::uninit(); 8];
+
+        let mut storage = SliceStorage::new(&mut buffer);
+        for value in &[3, 1, 4, 1, 5] {
+            assert!(storage.push(*value).is_ok());
+        }
+
+        // Values already in the storage are heapified.
+        let mut heap = LeonardoHeap::from_storage_by(
+            storage, |a: &i32, b: &i32| b.cmp(a),
+        );
+        heap.push(9);
+        heap.push(2);
+
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(1));
+
+        let storage = heap.into_storage();
+        assert_eq!(storage.len(), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_push_full_storage() {
+        let mut heap = LeonardoHeap::from_storage(
+            ArrayStorage::<i32, 2>::new(),
+        );
+        heap.push(1);
+        heap.push(2);
+        heap.push(3);
+    }
+
+    #[test]
+    fn test_array_storage_retain_dedup() {
+        let mut heap = LeonardoHeap::from_storage(
+            ArrayStorage::<i32, 64>::new(),
+        );
+        for input in shuffled(32).into_iter().chain(shuffled(32)) {
+            heap.push(input);
+        }
+
+        heap.retain(|&x| x % 3 != 0);
+        assert_eq!(heap.validate(), Ok(()));
+        assert_eq!(heap.len(), 42);
+
+        heap.dedup();
+        let outputs: Vec<i32> = heap.drain().collect();
+        let expected: Vec<i32> = (0..32).rev().filter(|x| x % 3 != 0).collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_no_debug_bound() {
+        // Deliberately does not implement `Debug`.
+        #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+        struct Job(i32);
+
+        let mut jobs: Vec<Job> = vec![Job(3), Job(1), Job(2)];
+        sort(&mut jobs);
+        heapify_slice(&mut jobs);
+
+        let mut heap: LeonardoHeap<Job> = jobs.into();
+        heap.push(Job(5));
+        heap.push(Job(4));
+
+        if let Some(mut top) = heap.peek_mut() {
+            top.0 = 0;
+        }
+        assert!(heap.pop().unwrap().0 == 4);
+
+        assert!(heap.iter().map(|job| job.0).eq(vec![3, 2, 1, 0]));
+
+        let mut min_heap = MinLeonardoHeap::new_min();
+        min_heap.extend(heap.drain());
+        assert!(min_heap.into_sorted_iter().map(|job| job.0).eq(0..4));
+
+        let mut bounded = BoundedLeonardoHeap::new(1);
+        bounded.push(Job(1));
+        assert!(bounded.push(Job(2)).unwrap().0 == 1);
+
+        let mut indexed = IndexedLeonardoHeap::new();
+        let handle = indexed.push(Job(1));
+        indexed.update(handle, Job(6));
+        assert!(indexed.pop().unwrap().1 == Job(6));
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut heap = LeonardoHeap::new();
+        assert_eq!(heap.peek(), None);
+
+        for value in &[3, 1, 4, 1, 5, 9, 2, 6] {
+            heap.push(*value);
+            assert_eq!(heap.peek(), heap.as_slice().iter().max());
+        }
+
+        assert_eq!(heap.peek(), Some(&9));
+        heap.pop();
+        assert_eq!(heap.peek(), Some(&6));
+    }
+
+    #[test]
+    fn test_peek_n() {
+        let mut heap: LeonardoHeap<i32> = shuffled(100).into();
+
+        assert_eq!(heap.peek_n(0), &[]);
+        assert_eq!(heap.peek_n(3), &[97, 98, 99]);
+        assert_eq!(heap.peek_n(5), &[95, 96, 97, 98, 99]);
+        assert_eq!(heap.len(), 100);
+
+        // The heap is still valid.
+        heap.push(100);
+        assert_eq!(heap.peek_n(2), &[99, 100]);
+
+        let expected: Vec<i32> = (0..101).collect();
+        assert_eq!(heap.peek_n(1000), expected.as_slice());
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        let expected: Vec<i32> = (0..101).rev().collect();
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_partial_sort_random() {
+        for len in 0..60 {
+            for k in 0..(len + 3) {
+                let mut inputs = shuffled(len);
+
+                partial_sort(&mut inputs, k as usize);
+
+                let sorted = cmp::min(k, len) as usize;
+                let start = inputs.len() - sorted;
+                let expected: Vec<i32> = (len - sorted as i32..len).collect();
+                assert_eq!(&inputs[start..], expected.as_slice());
+
+                // Nothing has been lost from the unsorted part.
+                let mut rest = inputs[..start].to_vec();
+                rest.sort();
+                let expected: Vec<i32> = (0..len - sorted as i32).collect();
+                assert_eq!(rest, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_sort_by() {
+        let mut inputs = vec![4, 8, 1, 9, 3, 7, 2];
+        partial_sort_by(&mut inputs, 3, |a, b| b.cmp(a));
+        assert_eq!(inputs[4..], [3, 2, 1]);
+
+        let mut inputs = vec!["ccc", "a", "dddd", "bb"];
+        partial_sort_by_key(&mut inputs, 2, |s| s.len());
+        assert_eq!(inputs[2..], ["ccc", "dddd"]);
+    }
+
+    #[test]
+    fn test_select_top_k() {
+        let mut inputs = vec![5, 3, 9, 1, 7];
+        assert_eq!(select_top_k(&mut inputs, 2), &[7, 9]);
+        assert_eq!(select_top_k(&mut inputs, 10), &[1, 3, 5, 7, 9]);
+        assert_eq!(select_top_k(&mut inputs, 0), &[]);
+    }
+
+    #[test]
+    fn test_validate_random() {
+        let mut rng = rand::thread_rng();
+
+        let mut heap = LeonardoHeap::new();
+        assert_eq!(heap.validate(), Ok(()));
+
+        for _ in 0..500 {
+            if rng.gen_range(0, 3) == 0 {
+                heap.pop();
+            } else {
+                heap.push(rng.gen_range(0, 100));
+            }
+            assert_eq!(heap.validate(), Ok(()));
+            assert_eq!(validate_slice(heap.as_slice()), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_validate_slice() {
+        for len in 0..50 {
+            let mut data: Vec<i32> = (0..len).rev().collect();
+            heapify_slice(&mut data);
+            assert_eq!(validate_slice(&data), Ok(()));
+            assert_eq!(validate_slice_by(&data, |a, b| a.cmp(b)), Ok(()));
+        }
+
+        // A single subheap of order 2 whose head is smaller than its children.
+        assert_eq!(
+            validate_slice(&[2, 1, 0]),
+            Err(InvariantError::HeapProperty { index: 2, order: 2 }),
+        );
+
+        // Subheaps of order 1 and 0, with the larger value in front.
+        assert_eq!(
+            validate_slice(&[2, 1]),
+            Err(InvariantError::StringProperty { index: 1, order: 0 }),
+        );
+
+        // A violation deep inside a subheap of order 4.
+        let mut data: Vec<i32> = (0..9).collect();
+        data.swap(3, 4);
+        assert_eq!(
+            validate_slice(&data),
+            Err(InvariantError::HeapProperty { index: 4, order: 3 }),
+        );
+
+        assert_eq!(validate_slice_by(&[2, 1], |a, b| b.cmp(a)), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "paranoid")]
+    #[should_panic(expected = "heap property violated")]
+    fn test_paranoid_push() {
+        let mut heap: LeonardoHeap<i32> = (0..100).collect();
+        heap.sort();
+
+        // Break a subheap that the push will not touch.
+        heap.data.swap(3, 4);
+        heap.push(1000);
+    }
 }
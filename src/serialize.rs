@@ -0,0 +1,134 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use compare::Compare;
+use storage::Storage;
+use LeonardoHeap;
+
+/// Heaps are serialized as the sequence of their elements, in heap order.  The
+/// comparator is not serialized.
+impl<T, C, S> Serialize for LeonardoHeap<T, C, S>
+    where T: Serialize, C: Compare<T>, S: Storage<T>
+{
+    fn serialize<R>(&self, serializer: R) -> Result<R::Ok, R::Error>
+        where R: Serializer
+    {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+
+/// Heaps are deserialized from a sequence of elements, and ordered using the
+/// default comparator.
+///
+/// The elements are always rearranged into heap order, and the layout is
+/// rebuilt from the number of elements, so a corrupted or hand-edited input
+/// can never produce a heap that violates the heap or string properties.
+impl<'de, T, C, S> Deserialize<'de> for LeonardoHeap<T, C, S>
+    where T: Deserialize<'de>, C: Compare<T> + Default, S: Storage<T> + Default
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let storage = deserializer.deserialize_seq(StorageVisitor {
+            marker: PhantomData,
+        })?;
+
+        Ok(LeonardoHeap::from_storage_by(storage, C::default()))
+    }
+}
+
+
+/// Collects a sequence of elements into a new storage, in the order that they
+/// were serialized.
+struct StorageVisitor<T, S> {
+    marker: PhantomData<(T, S)>,
+}
+
+
+impl<'de, T, S> Visitor<'de> for StorageVisitor<T, S>
+    where T: Deserialize<'de>, S: Storage<T> + Default
+{
+    type Value = S;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of heap elements")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<S, A::Error>
+        where A: SeqAccess<'de>
+    {
+        let mut storage = S::default();
+
+        while let Some(value) = seq.next_element()? {
+            if storage.push(value).is_err() {
+                // Fixed capacity storage has run out of space.
+                return Err(A::Error::invalid_length(storage.len() + 1, &self));
+            }
+        }
+
+        Ok(storage)
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use serde::de::Deserialize;
+    use serde::de::value::{Error, SeqDeserializer};
+    use serde_test::{Token, assert_tokens};
+    use serde_test::assert_de_tokens_error;
+
+    use {LeonardoHeap, Natural, ArrayStorage};
+
+    #[test]
+    fn test_round_trip() {
+        let heap: LeonardoHeap<i32> = vec![3, 1, 4, 1, 5].into();
+
+        let mut tokens = vec![Token::Seq { len: Some(5) }];
+        tokens.extend(heap.as_slice().iter().map(|&v| Token::I32(v)));
+        tokens.push(Token::SeqEnd);
+
+        assert_tokens(&heap, &tokens);
+    }
+
+    #[test]
+    fn test_deserialize_reheapifies() {
+        // Descending order violates the string property.
+        let values = vec![5, 4, 3, 2, 1];
+        let deserializer: SeqDeserializer<_, Error> =
+            SeqDeserializer::new(values.into_iter());
+
+        let mut heap: LeonardoHeap<i32> =
+            LeonardoHeap::deserialize(deserializer).unwrap();
+        assert_eq!(heap.validate(), Ok(()));
+
+        let outputs: Vec<i32> = heap.drain().collect();
+        assert_eq!(outputs, vec![5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_deserialize_storage_full() {
+        type Heap = LeonardoHeap<i32, Natural, ArrayStorage<i32, 2>>;
+
+        assert_de_tokens_error::<Heap>(
+            &[
+                Token::Seq { len: Some(3) },
+                Token::I32(1),
+                Token::I32(2),
+                Token::I32(3),
+            ],
+            "invalid length 3, expected a sequence of heap elements",
+        );
+    }
+}
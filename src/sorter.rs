@@ -0,0 +1,129 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::cmp;
+
+use compare::{Compare, Natural};
+use layout::Layout;
+use validate;
+use {build_heap, is_less, sort_top};
+
+/// Sorts a borrowed slice a few elements at a time, picking up where the
+/// previous call left off.
+///
+/// The slice is arranged into a leonardo heap when the sorter is created.
+/// Each call to `sort_next` then pops more of the largest remaining elements
+/// off the heap, growing a sorted suffix at the end of the slice, in
+/// ascending order.
+#[derive(Debug)]
+pub struct PartialSorter<'a, T: 'a, C = Natural> {
+    data: &'a mut [T],
+
+    /// The layout of the heap made up of the elements that have not yet been
+    /// sorted, at the start of `data`.
+    layout: Layout,
+    cmp: C,
+}
+
+
+impl<'a, T: Ord> PartialSorter<'a, T> {
+    /// Creates a new sorter that will sort `data` in ascending order.
+    pub fn new(data: &'a mut [T]) -> Self {
+        PartialSorter::new_by(data, Natural)
+    }
+}
+
+
+impl<'a, T, C: Compare<T>> PartialSorter<'a, T, C> {
+    /// Creates a new sorter that will sort `data` using the comparator `cmp`.
+    ///
+    /// Arranging the slice into a heap takes linear time.
+    pub fn new_by(data: &'a mut [T], cmp: C) -> Self {
+        let layout = build_heap(data, &mut is_less(&cmp));
+
+        PartialSorter {
+            data,
+            layout,
+            cmp,
+        }
+    }
+
+    /// Sorts up to `k` more elements, and returns the elements that were
+    /// sorted by this call.
+    ///
+    /// The returned elements are the largest of those remaining, in ascending
+    /// order, and make up the start of the sorted suffix.
+    pub fn sort_next(&mut self, k: usize) -> &[T] {
+        let unsorted = self.layout.len();
+        let k = cmp::min(k, unsorted);
+
+        sort_top(
+            &mut self.data[..unsorted], &mut self.layout, k,
+            &mut is_less(&self.cmp),
+        );
+        validate::paranoid_check(
+            &self.data[..unsorted - k], &self.layout, &mut is_less(&self.cmp),
+        );
+
+        &self.data[unsorted - k..unsorted]
+    }
+
+    /// Returns the elements that have been sorted so far.  These are the
+    /// largest elements of the slice, in ascending order.
+    pub fn sorted_suffix(&self) -> &[T] {
+        &self.data[self.layout.len()..]
+    }
+
+    /// Returns the number of elements that have not yet been sorted.
+    pub fn remaining(&self) -> usize {
+        self.layout.len()
+    }
+
+    /// Returns `true` if the whole slice has been sorted.
+    pub fn is_finished(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use sorter::PartialSorter;
+    use tests::shuffled;
+
+    #[test]
+    fn test_sort_in_pages() {
+        let mut data = shuffled(100);
+
+        let mut sorter = PartialSorter::new(&mut data);
+        assert_eq!(sorter.sorted_suffix(), &[]);
+        assert_eq!(sorter.remaining(), 100);
+
+        assert_eq!(sorter.sort_next(3), &[97, 98, 99]);
+        assert_eq!(sorter.sort_next(2), &[95, 96]);
+        assert_eq!(sorter.sorted_suffix(), &[95, 96, 97, 98, 99]);
+        assert_eq!(sorter.sort_next(0), &[]);
+
+        assert_eq!(sorter.sort_next(1000).len(), 95);
+        assert!(sorter.is_finished());
+        assert_eq!(sorter.sort_next(10), &[]);
+
+        let expected: Vec<i32> = (0..100).collect();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let mut data = vec![5, 3, 9, 1, 7];
+
+        let mut sorter = PartialSorter::new_by(&mut data, |a: &i32, b: &i32| {
+            b.cmp(a)
+        });
+        assert_eq!(sorter.sort_next(2), &[3, 1]);
+        assert_eq!(sorter.remaining(), 3);
+    }
+}
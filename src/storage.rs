@@ -0,0 +1,405 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::slice;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// A buffer that a `LeonardoHeap` can keep its elements in.
+///
+/// Values are only ever added and removed at the end of the storage.  The heap
+/// rearranges them in place through `as_mut_slice`.
+pub trait Storage<T> {
+    /// Returns the values currently held in the storage.
+    fn as_slice(&self) -> &[T];
+
+    /// Returns the values currently held in the storage as a mutable slice.
+    fn as_mut_slice(&mut self) -> &mut [T];
+
+    /// Returns the number of values that the storage can hold without
+    /// reallocating.
+    fn capacity(&self) -> usize;
+
+    /// Appends a value to the end of the storage, handing it back if there is
+    /// no room for it.
+    fn push(&mut self, value: T) -> Result<(), T>;
+
+    /// Removes and returns the last value in the storage, or `None` if it is
+    /// empty.
+    fn pop(&mut self) -> Option<T>;
+
+    /// Returns the number of values held in the storage.
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    /// Returns `true` if the storage holds no values.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the values after the first `len`, leaving the rest in place.
+    /// Does nothing if the storage holds `len` values or fewer.
+    fn truncate(&mut self, len: usize) {
+        while self.len() > len {
+            self.pop();
+        }
+    }
+
+    /// Removes and drops all values held in the storage.
+    fn clear(&mut self) {
+        self.truncate(0);
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+impl<T> Storage<T> for Vec<T> {
+    fn as_slice(&self) -> &[T] {
+        Vec::as_slice(self)
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        Vec::as_mut_slice(self)
+    }
+
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+
+    /// Appends a value, growing the vector if needed.  Never fails.
+    fn push(&mut self, value: T) -> Result<(), T> {
+        Vec::push(self, value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len)
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self)
+    }
+}
+
+
+// The fixed capacity storages below keep their values at the start of a buffer
+// of uninitialized memory, along with a count, `len`, of how many there are.
+// The first `len` slots in the buffer are always initialized, and the rest
+// never are.  The following helpers rely on, and maintain, that invariant.
+
+/// Returns the values at the start of `buffer`.
+///
+/// # Safety
+///
+/// The first `len` slots in `buffer` must be initialized, and `len` must be
+/// no greater than `buffer.len()`.
+unsafe fn initialized<T>(buffer: &[MaybeUninit<T>], len: usize) -> &[T] {
+    slice::from_raw_parts(buffer.as_ptr() as *const T, len)
+}
+
+/// Returns the values at the start of `buffer` as a mutable slice.
+///
+/// # Safety
+///
+/// The first `len` slots in `buffer` must be initialized, and `len` must be
+/// no greater than `buffer.len()`.
+unsafe fn initialized_mut<T>(
+    buffer: &mut [MaybeUninit<T>], len: usize,
+) -> &mut [T] {
+    slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut T, len)
+}
+
+/// Appends a value after the last one in `buffer`, handing it back if the
+/// buffer is full.
+fn push<T>(
+    buffer: &mut [MaybeUninit<T>], len: &mut usize, value: T,
+) -> Result<(), T> {
+    if *len == buffer.len() {
+        return Err(value);
+    }
+
+    buffer[*len] = MaybeUninit::new(value);
+    *len += 1;
+    Ok(())
+}
+
+/// Removes and returns the last value in `buffer`.
+fn pop<T>(buffer: &mut [MaybeUninit<T>], len: &mut usize) -> Option<T> {
+    if *len == 0 {
+        return None;
+    }
+
+    *len -= 1;
+    Some(unsafe { buffer[*len].assume_init_read() })
+}
+
+/// Drops the values in `buffer` after the first `new_len`.
+fn truncate<T>(
+    buffer: &mut [MaybeUninit<T>], len: &mut usize, new_len: usize,
+) {
+    if new_len >= *len {
+        return;
+    }
+
+    let values: *mut [T] = unsafe {
+        &mut initialized_mut(buffer, *len)[new_len..]
+    };
+
+    // Forget the values before dropping them so that a panic in one of their
+    // destructors can not lead to them being dropped twice.
+    *len = new_len;
+    unsafe { ptr::drop_in_place(values) };
+}
+
+
+/// Fixed capacity storage in a caller-provided buffer of uninitialized
+/// memory, such as a stack allocated array or a region of an arena.
+///
+/// Values still held in the storage are dropped when it is dropped.
+pub struct SliceStorage<'a, T: 'a> {
+    buffer: &'a mut [MaybeUninit<T>],
+    len: usize,
+}
+
+
+impl<'a, T> SliceStorage<'a, T> {
+    /// Creates a new, empty storage that will keep its values in `buffer`.
+    pub fn new(buffer: &'a mut [MaybeUninit<T>]) -> Self {
+        SliceStorage {
+            buffer,
+            len: 0,
+        }
+    }
+}
+
+
+impl<'a, T> Storage<T> for SliceStorage<'a, T> {
+    fn as_slice(&self) -> &[T] {
+        unsafe { initialized(self.buffer, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { initialized_mut(self.buffer, self.len) }
+    }
+
+    fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn push(&mut self, value: T) -> Result<(), T> {
+        push(self.buffer, &mut self.len, value)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        pop(self.buffer, &mut self.len)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        truncate(self.buffer, &mut self.len, len)
+    }
+}
+
+
+impl<'a, T> Drop for SliceStorage<'a, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+
+impl<'a, T: fmt::Debug> fmt::Debug for SliceStorage<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+
+/// Fixed capacity storage for up to `N` values held inline, without any heap
+/// allocation.
+pub struct ArrayStorage<T, const N: usize> {
+    buffer: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+
+impl<T, const N: usize> ArrayStorage<T, N> {
+    /// Creates a new, empty storage.
+    pub fn new() -> Self {
+        ArrayStorage {
+            buffer: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+}
+
+
+impl<T, const N: usize> Storage<T> for ArrayStorage<T, N> {
+    fn as_slice(&self) -> &[T] {
+        unsafe { initialized(&self.buffer, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { initialized_mut(&mut self.buffer, self.len) }
+    }
+
+    fn capacity(&self) -> usize {
+        N
+    }
+
+    fn push(&mut self, value: T) -> Result<(), T> {
+        push(&mut self.buffer, &mut self.len, value)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        pop(&mut self.buffer, &mut self.len)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        truncate(&mut self.buffer, &mut self.len, len)
+    }
+}
+
+
+impl<T, const N: usize> Drop for ArrayStorage<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+
+impl<T, const N: usize> Default for ArrayStorage<T, N> {
+    fn default() -> Self {
+        ArrayStorage::new()
+    }
+}
+
+
+impl<T: Clone, const N: usize> Clone for ArrayStorage<T, N> {
+    fn clone(&self) -> Self {
+        let mut storage = ArrayStorage::new();
+        for value in self.as_slice() {
+            // Can not fail as both storages have the same capacity.
+            let _ = storage.push(value.clone());
+        }
+        storage
+    }
+}
+
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayStorage<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::cell::Cell;
+    use std::mem::MaybeUninit;
+
+    use storage::{Storage, SliceStorage, ArrayStorage};
+
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl<'a> Drop for DropCounter<'a> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_array_storage_push_pop() {
+        let mut storage: ArrayStorage<i32, 3> = ArrayStorage::new();
+        assert_eq!(storage.capacity(), 3);
+        assert!(storage.is_empty());
+
+        assert_eq!(storage.push(1), Ok(()));
+        assert_eq!(storage.push(2), Ok(()));
+        assert_eq!(storage.push(3), Ok(()));
+        assert_eq!(storage.push(4), Err(4));
+        assert_eq!(storage.as_slice(), &[1, 2, 3]);
+
+        storage.as_mut_slice().swap(0, 2);
+        assert_eq!(storage.pop(), Some(1));
+        assert_eq!(storage.pop(), Some(2));
+        assert_eq!(storage.pop(), Some(3));
+        assert_eq!(storage.pop(), None);
+    }
+
+    #[test]
+    fn test_slice_storage_push_pop() {
+        let mut buffer = [MaybeUninit::uninit(); 2];
+        let mut storage = SliceStorage::new(&mut buffer);
+        assert_eq!(storage.capacity(), 2);
+
+        assert_eq!(storage.push(1), Ok(()));
+        assert_eq!(storage.push(2), Ok(()));
+        assert_eq!(storage.push(3), Err(3));
+        assert_eq!(storage.len(), 2);
+
+        assert_eq!(storage.pop(), Some(2));
+        assert_eq!(storage.pop(), Some(1));
+        assert_eq!(storage.pop(), None);
+    }
+
+    #[test]
+    fn test_storage_drops_values() {
+        let drops = Cell::new(0);
+
+        {
+            let mut storage: ArrayStorage<DropCounter, 4> =
+                ArrayStorage::new();
+            for _ in 0..3 {
+                assert!(storage.push(DropCounter(&drops)).is_ok());
+            }
+            storage.pop();
+            assert_eq!(drops.get(), 1);
+        }
+        assert_eq!(drops.get(), 3);
+
+        {
+            let mut buffer: [MaybeUninit<DropCounter>; 4] =
+                [const { MaybeUninit::uninit() }; 4];
+            let mut storage = SliceStorage::new(&mut buffer);
+            for _ in 0..3 {
+                assert!(storage.push(DropCounter(&drops)).is_ok());
+            }
+            storage.clear();
+            assert_eq!(drops.get(), 6);
+        }
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_storage_truncate() {
+        let drops = Cell::new(0);
+
+        let mut storage: ArrayStorage<DropCounter, 4> = ArrayStorage::new();
+        for _ in 0..4 {
+            assert!(storage.push(DropCounter(&drops)).is_ok());
+        }
+
+        storage.truncate(5);
+        assert_eq!(storage.len(), 4);
+        storage.truncate(1);
+        assert_eq!(storage.len(), 1);
+        assert_eq!(drops.get(), 3);
+
+        drop(storage);
+        assert_eq!(drops.get(), 4);
+    }
+}
@@ -5,10 +5,15 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::fmt::Debug;
-
 use leonardo::leonardo;
 
+/// The pair of child subheaps that make up the body of a subheap of order two
+/// or greater.
+type Children<'a, T> = Option<(SubHeap<'a, T>, SubHeap<'a, T>)>;
+
+/// Mutable equivalent of `Children`.
+type ChildrenMut<'a, T> = Option<(SubHeapMut<'a, T>, SubHeapMut<'a, T>)>;
+
 #[derive(Clone, Debug)]
 pub struct SubHeap<'a, T: 'a> {
     data: &'a [T],
@@ -16,19 +21,19 @@ pub struct SubHeap<'a, T: 'a> {
 }
 
 #[allow(dead_code)]
-impl<'a, T: Ord + Debug> SubHeap<'a, T> {
-    pub fn new(data: &[T], order: u32) -> SubHeap<T> {
+impl<'a, T> SubHeap<'a, T> {
+    pub fn new(data: &[T], order: u32) -> SubHeap<'_, T> {
         assert_eq!(data.len(), leonardo(order));
 
         SubHeap {
-            data: data,
-            order: order,
+            data,
+            order,
         }
     }
 
     /// Unpacks the head and, if they exist, the two smaller subheaps that make
     /// up the rest of this subheap.
-    pub fn destructure(&self) -> (&T, Option<(SubHeap<T>, SubHeap<T>)>) {
+    pub fn destructure(&self) -> (&T, Children<'_, T>) {
         if self.order > 1 {
             let fst_order = self.order - 2;
             let snd_order = self.order - 1;
@@ -54,7 +59,7 @@ impl<'a, T: Ord + Debug> SubHeap<'a, T> {
     /// If the subheap is of third order or greater returns references to the
     /// two child subheaps containing all values below the head.
     #[inline]
-    pub fn children(&self) -> Option<(SubHeap<T>, SubHeap<T>)> {
+    pub fn children(&self) -> Children<'_, T> {
         let (_, children) = self.destructure();
         children
     }
@@ -65,24 +70,37 @@ impl<'a, T: Ord + Debug> SubHeap<'a, T> {
 pub struct SubHeapMut<'a, T: 'a> {
     data: &'a mut [T],
     pub order: u32,
+
+    /// The index of the first value of the subheap in the data of the heap
+    /// that it is part of.
+    pub offset: usize,
 }
 
 
 #[allow(dead_code)]
-impl<'a, T: Ord + Debug> SubHeapMut<'a, T> {
-    pub fn new(data: &mut [T], order: u32) -> SubHeapMut<T> {
+impl<'a, T> SubHeapMut<'a, T> {
+    pub fn new(data: &mut [T], order: u32) -> SubHeapMut<'_, T> {
+        SubHeapMut::new_at(data, order, 0)
+    }
+
+    /// Creates a subheap covering `data`, which starts at index `offset` in
+    /// the data of the heap that it is part of.
+    pub fn new_at(
+        data: &mut [T], order: u32, offset: usize,
+    ) -> SubHeapMut<'_, T> {
         assert_eq!(data.len(), leonardo(order));
 
         SubHeapMut {
-            data: data,
-            order: order,
+            data,
+            order,
+            offset,
         }
     }
 
     /// Returns references to the head and, if they exist, the two smaller
     /// subheaps that make up the rest of this subheap allowing them to be read
     /// simultaneously.
-    pub fn destructure(&self) -> (&T, Option<(SubHeap<T>, SubHeap<T>)>) {
+    pub fn destructure(&self) -> (&T, Children<'_, T>) {
         if self.order > 1 {
             let fst_order = self.order - 2;
             let snd_order = self.order - 1;
@@ -102,7 +120,7 @@ impl<'a, T: Ord + Debug> SubHeapMut<'a, T> {
     /// Returns mutable references to the head and, if this subheap is of third
     /// order or greater, the two smaller subheaps that make up the rest
     /// allowing them to be read and modified simultaneously.
-    pub fn destructure_mut(&mut self) -> (&mut T, Option<(SubHeapMut<T>, SubHeapMut<T>)>) {
+    pub fn destructure_mut(&mut self) -> (&mut T, ChildrenMut<'_, T>) {
         if self.order > 1 {
             let fst_order = self.order - 2;
             let snd_order = self.order - 1;
@@ -112,9 +130,12 @@ impl<'a, T: Ord + Debug> SubHeapMut<'a, T> {
                 leonardo(snd_order),
             );
 
+            let snd_offset = self.offset;
+            let fst_offset = snd_offset + leonardo(snd_order);
+
             (value, Some((
-                SubHeapMut::new(fst_data, fst_order),
-                SubHeapMut::new(snd_data, snd_order),
+                SubHeapMut::new_at(fst_data, fst_order, fst_offset),
+                SubHeapMut::new_at(snd_data, snd_order, snd_offset),
             )))
         } else {
             (self.value_mut(), None)
@@ -123,9 +144,7 @@ impl<'a, T: Ord + Debug> SubHeapMut<'a, T> {
 
     /// Breaks the subheap up into its constituent parts: the head and for
     /// subheaps of third order or greater the two child subheaps.
-    pub fn into_components(self) -> (
-        &'a mut T, Option<(SubHeapMut<'a, T>, SubHeapMut<'a, T>)>,
-    ) {
+    pub fn into_components(self) -> (&'a mut T, ChildrenMut<'a, T>) {
         if self.order > 1 {
             let fst_order = self.order - 2;
             let snd_order = self.order - 1;
@@ -135,9 +154,12 @@ impl<'a, T: Ord + Debug> SubHeapMut<'a, T> {
                 leonardo(snd_order),
             );
 
+            let snd_offset = self.offset;
+            let fst_offset = snd_offset + leonardo(snd_order);
+
             (value, Some((
-                SubHeapMut::new(fst_data, fst_order),
-                SubHeapMut::new(snd_data, snd_order),
+                SubHeapMut::new_at(fst_data, fst_order, fst_offset),
+                SubHeapMut::new_at(snd_data, snd_order, snd_offset),
             )))
         } else {
             (self.into_value(), None)
@@ -156,6 +178,13 @@ impl<'a, T: Ord + Debug> SubHeapMut<'a, T> {
         self.data.last_mut().unwrap()
     }
 
+    /// Returns the index of the head of the subheap in the data of the heap
+    /// that it is part of.
+    #[inline]
+    pub fn head_index(&self) -> usize {
+        self.offset + self.data.len() - 1
+    }
+
     /// Consumes a subheap and returns a mutable reference to the value at the
     /// head of the data that it points to.
     #[inline]
@@ -164,19 +193,21 @@ impl<'a, T: Ord + Debug> SubHeapMut<'a, T> {
     }
 
     #[inline]
-    fn children(&self) -> Option<(SubHeap<T>, SubHeap<T>)> {
+    fn children(&self) -> Children<'_, T> {
         let (_, children) = self.destructure();
         children
     }
 
+    /// If the subheap is of third order or greater returns mutable references
+    /// to the two child subheaps containing all values below the head.
     #[inline]
-    fn children_mut(&mut self) -> Option<(SubHeapMut<T>, SubHeapMut<T>)> {
+    pub fn children_mut(&mut self) -> ChildrenMut<'_, T> {
         let (_, children) = self.destructure_mut();
         children
     }
 
     #[inline]
-    fn into_children(self) -> Option<(SubHeapMut<'a, T>, SubHeapMut<'a, T>)> {
+    fn into_children(self) -> ChildrenMut<'a, T> {
         let (_, children) = self.into_components();
         children
     }
@@ -0,0 +1,179 @@
+// Copyright 2016 Ben Mather <bwhmather@bwhmather.com>
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::fmt;
+
+use layout::Layout;
+use leonardo::leonardo;
+use subheap::SubHeap;
+use HeapOps;
+
+/// Describes a place where data that should be arranged as a leonardo heap
+/// breaks one of the heap's invariants.
+///
+/// Indexes are positions in the underlying storage, as returned by
+/// `LeonardoHeap::as_slice`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InvariantError {
+    /// The head of a subheap, at `index`, is smaller than the head of one of
+    /// its children.
+    HeapProperty {
+        index: usize,
+        order: u32,
+    },
+
+    /// The head of a top-level subheap, at `index`, is smaller than the head
+    /// of the top-level subheap in front of it.
+    StringProperty {
+        index: usize,
+        order: u32,
+    },
+}
+
+
+impl InvariantError {
+    /// Returns the index of the head of the subheap that breaks the
+    /// invariant.
+    pub fn index(&self) -> usize {
+        match *self {
+            InvariantError::HeapProperty { index, .. } => index,
+            InvariantError::StringProperty { index, .. } => index,
+        }
+    }
+
+    /// Returns the order of the subheap that breaks the invariant.
+    pub fn order(&self) -> u32 {
+        match *self {
+            InvariantError::HeapProperty { order, .. } => order,
+            InvariantError::StringProperty { order, .. } => order,
+        }
+    }
+}
+
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InvariantError::HeapProperty { index, order } => write!(
+                f, "heap property violated by subheap of order {} at index {}",
+                order, index,
+            ),
+            InvariantError::StringProperty { index, order } => write!(
+                f,
+                "string property violated by subheap of order {} at index {}",
+                order, index,
+            ),
+        }
+    }
+}
+
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for InvariantError {}
+
+
+/// Checks that every value in a subheap starting at `offset` is no larger than
+/// the head of the subheap it belongs to.
+fn check_subheap<T, O>(
+    heap: &SubHeap<T>, offset: usize, ops: &mut O,
+) -> Result<(), InvariantError>
+    where O: HeapOps<T>
+{
+    let (value, children) = heap.destructure();
+
+    if let Some((fst_child, snd_child)) = children {
+        if ops.is_less(value, fst_child.value())
+            || ops.is_less(value, snd_child.value())
+        {
+            return Err(InvariantError::HeapProperty {
+                index: offset + leonardo(heap.order) - 1,
+                order: heap.order,
+            });
+        }
+
+        // The second, larger, child comes first in the underlying data.
+        let fst_offset = offset + leonardo(snd_child.order);
+
+        check_subheap(&snd_child, offset, ops)?;
+        check_subheap(&fst_child, fst_offset, ops)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that data described by `layout` satisfies both the heap property,
+/// for every subheap, and the string property, for the top-level subheaps.
+///
+/// Returns the first violation found, starting from the end of the data.
+pub fn check_heap<T, O>(
+    heap_data: &[T], layout: &Layout, ops: &mut O,
+) -> Result<(), InvariantError>
+    where O: HeapOps<T>
+{
+    let mut end = heap_data.len();
+    let mut next: Option<(usize, u32)> = None;
+
+    for subheap in layout.iter_ref(heap_data) {
+        let start = end - leonardo(subheap.order);
+
+        check_subheap(&subheap, start, ops)?;
+
+        // The head of the following subheap must be at least as large as the
+        // head of this one.
+        if let Some((next_index, next_order)) = next {
+            if ops.is_less(&heap_data[next_index], subheap.value()) {
+                return Err(InvariantError::StringProperty {
+                    index: next_index,
+                    order: next_order,
+                });
+            }
+        }
+
+        next = Some((end - 1, subheap.order));
+        end = start;
+    }
+
+    Ok(())
+}
+
+/// Panics, with a dump of the layout, if `check_heap` finds a violation.
+///
+/// Does nothing unless the `paranoid` feature is enabled, in which case it is
+/// called after every operation that modifies a heap.
+#[inline]
+pub fn paranoid_check<T, O>(heap_data: &[T], layout: &Layout, ops: &mut O)
+    where O: HeapOps<T>
+{
+    if cfg!(feature = "paranoid") {
+        if let Err(error) = check_heap(heap_data, layout, ops) {
+            panic!("{} in heap with {:?}", error, layout);
+        }
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use validate::InvariantError;
+
+    #[test]
+    fn test_display() {
+        let error = InvariantError::HeapProperty { index: 8, order: 4 };
+        assert_eq!(
+            error.to_string(),
+            "heap property violated by subheap of order 4 at index 8",
+        );
+        assert_eq!(error.index(), 8);
+        assert_eq!(error.order(), 4);
+
+        let error = InvariantError::StringProperty { index: 1, order: 0 };
+        assert_eq!(
+            error.to_string(),
+            "string property violated by subheap of order 0 at index 1",
+        );
+    }
+}